@@ -109,6 +109,21 @@ fn main() {
             println!("{:?}", unit);
         }
 
+        // Or define the optics on the struct itself to reuse the query
+        #[derive(Debug, Query)]
+        #[query(archetype = "StructOf<Vec<Unit>>")]
+        struct UnitView<'a> {
+            id: usize,
+            pos: &'a (f32, f32),
+            #[query(optic = "damage.Get.Some")]
+            damage: &'a f32,
+        }
+
+        println!("\nQuerying into a reusable view:");
+        for unit in UnitView::query(&world.units) {
+            println!("{:?}", unit);
+        }
+
         // Or just query into a tuple
         println!("\nQuerying into a tuple:");
         for unit in query!(world.units, (id, &pos, &tick)) {
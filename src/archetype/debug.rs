@@ -0,0 +1,47 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+/// Format the entities of an archetype as a table, used by the generated `Debug` impls
+/// (re-exported in `__private`).
+///
+/// Each row contains the formatted id and components of an entity, in the same order as the `header`.
+pub fn debug_table(
+    f: &mut fmt::Formatter<'_>,
+    header: &[&str],
+    rows: &[Vec<String>],
+) -> fmt::Result {
+    let widths: Vec<usize> = header
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([name.chars().count()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let format_row = |cells: &[&str]| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, &width)| format!("{cell:width$}"))
+            .collect::<Vec<_>>()
+            .join(" | ");
+        line.trim_end().to_string()
+    };
+
+    let separator: Vec<String> = widths.iter().map(|&width| "-".repeat(width)).collect();
+    let mut lines = vec![format_row(header), separator.join("-+-")];
+    lines.extend(rows.iter().map(|row| {
+        let cells: Vec<&str> = row.iter().map(String::as_str).collect();
+        format_row(&cells)
+    }));
+    write!(f, "{}", lines.join("\n"))
+}
@@ -0,0 +1,212 @@
+/// A single character of a field name on the type level.
+///
+/// Field names are encoded as lists of characters, e.g. `(Char<'i'>, (Char<'d'>, ()))`, see [`field!`](crate::field).
+pub struct Char<const C: char>;
+
+/// Compares two type-level field names, made with [`field!`](crate::field).
+///
+/// Used to check at compile time that the fields borrowed with [`Fields::fields_mut`] are distinct.
+pub trait SameName<N> {
+    /// Whether the names are the same.
+    const SAME: bool;
+}
+
+impl SameName<()> for () {
+    const SAME: bool = true;
+}
+
+impl<const C: char, T> SameName<(Char<C>, T)> for () {
+    const SAME: bool = false;
+}
+
+impl<const C: char, T> SameName<()> for (Char<C>, T) {
+    const SAME: bool = false;
+}
+
+impl<const C1: char, const C2: char, T1: SameName<T2>, T2> SameName<(Char<C2>, T2)>
+    for (Char<C1>, T1)
+{
+    const SAME: bool = C1 == C2 && T1::SAME;
+}
+
+/// Implemented for archetypes that have a field with the name `N`, made with [`field!`](crate::field).
+///
+/// Allows writing functions that are generic over any archetype having the required fields.
+///
+/// # Safety
+/// [`HasField::field_ptr`] must return a pointer to a field inside `this`,
+/// and implementations for different names must point to different (non-overlapping) fields.
+///
+pub unsafe trait HasField<N> {
+    /// Type of the field: either a [`Storage`](crate::storage::Storage), a [`TagStorage`](crate::storage::bitset::TagStorage), or a nested archetype.
+    type Storage;
+    /// Get an immutable reference to the field.
+    fn get_field(&self) -> &Self::Storage;
+    /// Get a mutable reference to the field.
+    fn get_field_mut(&mut self) -> &mut Self::Storage;
+    /// Get a pointer to the field.
+    ///
+    /// # Safety
+    /// `this` must be valid for reads and writes.
+    ///
+    unsafe fn field_ptr(this: *mut Self) -> *mut Self::Storage;
+}
+
+/// A tuple of field names of the archetype `A`, which can be borrowed mutably at the same time,
+/// see [`Fields::fields_mut`].
+///
+/// Implemented for tuples of up to 12 names.
+///
+/// # Safety
+/// [`FieldNames::fields_mut`] must return references to non-overlapping fields.
+///
+pub unsafe trait FieldNames<A: ?Sized>: 'static {
+    /// The tuple of mutable references to the storages of the fields.
+    type Storages<'a>
+    where
+        A: 'a;
+    /// Whether all of the names are different.
+    const DISTINCT: bool;
+    /// Get mutable references to the fields.
+    ///
+    /// Fails to compile if the same name is repeated.
+    fn fields_mut(archetype: &mut A) -> Self::Storages<'_>;
+}
+
+macro_rules! impl_field_names {
+    ($($name:ident),+) => {
+        impl_field_names!(@munch [$($name),+] [] [true] $($name)+);
+    };
+    (@munch [$($name:ident),+] [$($bounds:tt)*] [$($distinct:tt)*] $first:ident $($rest:ident)*) => {
+        impl_field_names!(
+            @munch [$($name),+]
+            [$($bounds)* $first: $(SameName<$rest> +)* Sized,]
+            [$($distinct)* $(&& !<$first as SameName<$rest>>::SAME)*]
+            $($rest)*
+        );
+    };
+    (@munch [$($name:ident),+] [$($bounds:tt)*] [$($distinct:tt)*]) => {
+        unsafe impl<A: ?Sized $(+ HasField<$name>)+, $($name: 'static),+> FieldNames<A> for ($($name,)+)
+        where
+            $($bounds)*
+        {
+            type Storages<'a>
+                = ($(&'a mut <A as HasField<$name>>::Storage,)+)
+            where
+                A: 'a;
+            const DISTINCT: bool = $($distinct)*;
+            fn fields_mut(archetype: &mut A) -> Self::Storages<'_> {
+                const { assert!(<Self as FieldNames<A>>::DISTINCT, "cannot borrow the same field mutably twice") };
+                let this: *mut A = archetype;
+                // SAFETY: `this` comes from a mutable reference,
+                // and the fields do not overlap, since the names are different
+                unsafe { ($(&mut *<A as HasField<$name>>::field_ptr(this),)+) }
+            }
+        }
+    };
+}
+
+impl_field_names!(N1);
+impl_field_names!(N1, N2);
+impl_field_names!(N1, N2, N3);
+impl_field_names!(N1, N2, N3, N4);
+impl_field_names!(N1, N2, N3, N4, N5);
+impl_field_names!(N1, N2, N3, N4, N5, N6);
+impl_field_names!(N1, N2, N3, N4, N5, N6, N7);
+impl_field_names!(N1, N2, N3, N4, N5, N6, N7, N8);
+impl_field_names!(N1, N2, N3, N4, N5, N6, N7, N8, N9);
+impl_field_names!(N1, N2, N3, N4, N5, N6, N7, N8, N9, N10);
+impl_field_names!(N1, N2, N3, N4, N5, N6, N7, N8, N9, N10, N11);
+impl_field_names!(N1, N2, N3, N4, N5, N6, N7, N8, N9, N10, N11, N12);
+
+/// Methods to access the fields of archetypes by name, implemented for all types.
+///
+/// Not in the prelude, since its methods would shadow the inherent ones with the same names.
+///
+/// # Example
+///
+/// ```
+/// # use stecs::prelude::*;
+/// use stecs::archetype::Fields;
+///
+/// #[derive(SplitFields)]
+/// struct Unit {
+///     pos: f32,
+///     vel: f32,
+/// }
+///
+/// fn move_all<F, A>(archetype: &mut A)
+/// where
+///     F: StorageFamily,
+///     A: Archetype<F>
+///         + HasField<field!(ids), Storage = F::Storage<()>>
+///         + HasField<field!(pos), Storage = F::Storage<f32>>
+///         + HasField<field!(vel), Storage = F::Storage<f32>>,
+/// {
+///     for (pos, vel) in query!(impl archetype, (&mut pos, &vel)) {
+///         *pos += *vel;
+///     }
+/// }
+///
+/// fn swap<F, A>(archetype: &mut A)
+/// where
+///     F: StorageFamily,
+///     A: Archetype<F>
+///         + HasField<field!(pos), Storage = F::Storage<f32>>
+///         + HasField<field!(vel), Storage = F::Storage<f32>>,
+/// {
+///     let (pos, vel) = archetype.fields_mut::<(field!(pos), field!(vel))>();
+///     core::mem::swap(pos, vel);
+/// }
+///
+/// let mut units: StructOf<Vec<Unit>> = Default::default();
+/// let id = units.insert(Unit { pos: 1.0, vel: 2.0 });
+/// move_all(&mut units);
+/// assert_eq!(units.field::<field!(pos)>().get(id), Some(&3.0));
+/// swap(&mut units);
+/// assert_eq!(units.field::<field!(vel)>().get(id), Some(&3.0));
+/// ```
+///
+/// Borrowing the same field twice fails to compile:
+///
+/// ```compile_fail
+/// # use stecs::prelude::*;
+/// use stecs::archetype::Fields;
+///
+/// #[derive(SplitFields)]
+/// struct Unit {
+///     pos: f32,
+/// }
+///
+/// let mut units: StructOf<Vec<Unit>> = Default::default();
+/// let (a, b) = units.fields_mut::<(field!(pos), field!(pos))>();
+/// ```
+///
+pub trait Fields {
+    /// Get an immutable reference to the field `N`.
+    fn field<N>(&self) -> &<Self as HasField<N>>::Storage
+    where
+        Self: HasField<N>,
+    {
+        HasField::<N>::get_field(self)
+    }
+
+    /// Get a mutable reference to the field `N`.
+    fn field_mut<N>(&mut self) -> &mut <Self as HasField<N>>::Storage
+    where
+        Self: HasField<N>,
+    {
+        HasField::<N>::get_field_mut(self)
+    }
+
+    /// Get mutable references to several different fields at the same time,
+    /// given as a tuple of names, see [`FieldNames`].
+    ///
+    /// Fails to compile if the same field is borrowed twice.
+    ///
+    fn fields_mut<N: FieldNames<Self>>(&mut self) -> N::Storages<'_> {
+        N::fields_mut(self)
+    }
+}
+
+impl<T: ?Sized> Fields for T {}
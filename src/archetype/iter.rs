@@ -2,16 +2,14 @@ use super::*;
 
 /// Iterator over the entities in an [Archetype].
 pub struct ArchetypeIntoIter<F: StorageFamily, S: Archetype<F>> {
-    ids: std::vec::IntoIter<F::Id>,
-    archetype: S,
+    drain: S::Drain,
 }
 
 impl<F: StorageFamily, S: Archetype<F>> ArchetypeIntoIter<F, S> {
-    /// Construct an iterator, consuming the archetype.
+    /// Construct an iterator, consuming the archetype, see [`Archetype::into_drain`].
     pub fn new(archetype: S) -> Self {
         Self {
-            ids: archetype.ids().collect::<Vec<_>>().into_iter(), // TODO: without collecting
-            archetype,
+            drain: archetype.into_drain(),
         }
     }
 }
@@ -20,8 +18,10 @@ impl<F: StorageFamily, S: Archetype<F>> Iterator for ArchetypeIntoIter<F, S> {
     type Item = (F::Id, S::Item);
 
     fn next(&mut self) -> Option<Self::Item> {
-        let id = self.ids.next()?;
-        let item = self.archetype.remove(id)?;
-        Some((id, item))
+        self.drain.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.drain.size_hint()
     }
 }
@@ -1,19 +1,210 @@
+pub(crate) mod debug;
+mod field;
 mod iter;
+mod record;
 
+pub use self::field::*;
 pub use self::iter::*;
+pub use self::record::*;
 
-use crate::storage::StorageFamily;
+use crate::storage::{option::OptionFamily, InsertError, StorageFamily};
+
+use alloc::vec::Vec;
+use core::hash::Hash;
+use hashbrown::HashMap;
 
 /// A collection of components bundled together, or an entity type, or a generic SoA (struct of arrays).
 pub trait Archetype<F: StorageFamily>: Default {
     /// The type of the entity stored as components.
     type Item;
+    /// Type of the owning iterator returned by [`Archetype::drain`].
+    type Drain: Iterator<Item = (F::Id, Self::Item)>;
     /// Return id's of all active entities.
     fn ids(&self) -> impl Iterator<Item = F::Id>;
     /// Insert a new entity, returning its id.
+    ///
+    /// # Panics
+    /// May panic if any of the storages rejects the component, see [`Archetype::try_insert`].
+    ///
     fn insert(&mut self, value: Self::Item) -> F::Id;
+    /// Insert a new entity, returning its id, or returning it back if any of the storages rejects it,
+    /// see [`Storage::try_insert`](crate::storage::Storage::try_insert).
+    ///
+    /// The archetype is left unchanged on failure.
+    /// See [`ArrayVecFamily`](crate::storage::arrayvec::ArrayVecFamily) for an example.
+    fn try_insert(&mut self, value: Self::Item) -> Result<F::Id, InsertError<Self::Item>> {
+        Ok(self.insert(value))
+    }
+    /// Insert a new entity with a given id, see [`Storage::insert_with_id`](crate::storage::Storage::insert_with_id).
+    ///
+    /// Fails, returning the value back, if the id is already occupied or cannot be used.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use stecs::prelude::*;
+    /// #[derive(SplitFields)]
+    /// struct Unit {
+    ///     health: i64,
+    /// }
+    ///
+    /// let mut units: StructOf<Vec<Unit>> = Default::default();
+    /// assert!(units.insert_with_id(0, Unit { health: 5 }).is_ok());
+    /// // The id is already occupied
+    /// assert!(units.insert_with_id(0, Unit { health: 3 }).is_err());
+    /// ```
+    ///
+    fn insert_with_id(&mut self, id: F::Id, value: Self::Item) -> Result<(), Self::Item>;
     /// Remove an entity with a given id.
     fn remove(&mut self, id: F::Id) -> Option<Self::Item>;
+    /// Replace the entity with a given id, keeping its id, and return the old value.
+    ///
+    /// Fails, returning the value back, if the entity is absent,
+    /// or if the archetype does not support it (by default, replacing is not supported).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use stecs::prelude::*;
+    /// #[derive(SplitFields, Debug, PartialEq)]
+    /// struct Unit {
+    ///     health: i64,
+    /// }
+    ///
+    /// let mut units: StructOf<Vec<Unit>> = Default::default();
+    /// let id = units.insert(Unit { health: 5 });
+    /// assert_eq!(units.replace(id, Unit { health: 3 }), Ok(Unit { health: 5 }));
+    /// assert_eq!(units.get(id).map(|unit| *unit.health), Some(3));
+    /// ```
+    ///
+    fn replace(&mut self, id: F::Id, value: Self::Item) -> Result<Self::Item, Self::Item> {
+        let _ = id;
+        Err(value)
+    }
+    /// Remove all entities, returning an iterator over them together with their id's.
+    ///
+    /// The components are moved out of the storages column by column, see [`Storage::drain`](crate::storage::Storage::drain).
+    fn drain(&mut self) -> Self::Drain;
+    /// Consume the archetype, returning an iterator over the entities together with their id's.
+    ///
+    /// Unlike [`Archetype::drain`], the entities are moved out of an archetype that is going away,
+    /// so `#[derive(SplitFields)]` does not call the `on_remove` hooks for them.
+    fn into_drain(mut self) -> Self::Drain {
+        self.drain()
+    }
+    /// Take the entity with a given id out of a drain, see [`Storage::drain_take`](crate::storage::Storage::drain_take).
+    fn drain_take(drain: &mut Self::Drain, id: F::Id) -> Option<Self::Item> {
+        let _ = id;
+        drain.next().map(|(_, value)| value)
+    }
+    /// Rebuild the archetype, inserting the entities in the given order, and return their new id's.
+    ///
+    /// `order` must contain each id of the archetype exactly once, see [`Storage::reorder`](crate::storage::Storage::reorder).
+    ///
+    /// The default implementation removes and reinserts the entities one by one,
+    /// `#[derive(SplitFields)]` overrides it to reorder the storages column by column.
+    fn reorder(&mut self, order: &[F::Id]) -> Vec<F::Id> {
+        let mut old = core::mem::take(self);
+        order
+            .iter()
+            .map(|&id| {
+                let value = old.remove(id).expect("invalid id: entry absent");
+                self.insert(value)
+            })
+            .collect()
+    }
+
+    /// Rebuild the storages densely, returning the map from old to new id's.
+    ///
+    /// Useful after many removals, which leave the storages (e.g. `Arena` or `HashStorage`) sparse and slow to iterate.
+    /// The id's are kept the same where the family allows it (e.g. `HashStorage`).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use stecs::prelude::*;
+    /// # use stecs::storage::arena::Arena;
+    /// #[derive(SplitFields)]
+    /// struct Unit {
+    ///     health: i64,
+    /// }
+    ///
+    /// let mut units: StructOf<Arena<Unit>> = Default::default();
+    /// let ids: Vec<_> = (0..10).map(|health| units.insert(Unit { health })).collect();
+    /// for &id in &ids[..9] {
+    ///     units.remove(id);
+    /// }
+    ///
+    /// let remap = units.compact();
+    /// let last = remap[&ids[9]];
+    /// assert_eq!(units.get(last).map(|unit| *unit.health), Some(9));
+    /// ```
+    ///
+    fn compact(&mut self) -> HashMap<F::Id, F::Id>
+    where
+        F::Id: Eq + Hash,
+    {
+        let order: Vec<_> = self.ids().collect();
+        let new_ids = self.reorder(&order);
+        order.into_iter().zip(new_ids).collect()
+    }
+}
+
+/// Borrowing access to the entities of an [Archetype], i.e. the `get` and `get_mut` methods of `XStructOf`.
+///
+/// Implemented by `#[derive(SplitFields)]` for structs without lifetime parameters.
+pub trait ArchetypeRef<F: StorageFamily>: Archetype<F>
+where
+    Self::Item: StructRef,
+{
+    /// Get an immutable reference to all components of an entity.
+    fn get(&self, id: F::Id) -> Option<<Self::Item as StructRef>::Ref<'_>>;
+    /// Get a mutable reference to all components of an entity.
+    fn get_mut(&mut self, id: F::Id) -> Option<<Self::Item as StructRef>::RefMut<'_>>;
+}
+
+/// An [Archetype] stored in an [`OptionFamily`], so that its entities may be absent.
+///
+/// Implemented by `#[derive(SplitFields)]`, and used for `#[split(nested)]` fields of type `Option<T>`.
+pub trait OptionalArchetype<F: StorageFamily>: Archetype<OptionFamily<F>> {
+    /// Type of the owning iterator returned by [`OptionalArchetype::drain_optional`].
+    type DrainOptional: Iterator<Item = (F::Id, Option<Self::Item>)>;
+    /// Insert an absent entity, returning its id.
+    fn insert_none(&mut self) -> F::Id;
+    /// Insert an absent entity, returning its id, or an error if any of the storages rejects it.
+    fn try_insert_none(&mut self) -> Result<F::Id, InsertError<()>> {
+        Ok(self.insert_none())
+    }
+    /// Insert an absent entity with a given id.
+    /// Returns `false` if the id is already occupied or cannot be used.
+    fn insert_none_with_id(&mut self, id: F::Id) -> bool;
+    /// Remove an entity with a given id, whether it is present or absent.
+    /// Returns `None` if the id is invalid.
+    fn take(&mut self, id: F::Id) -> Option<Option<Self::Item>>;
+    /// Replace an entity with a given id, whether it is present or absent, keeping its id,
+    /// and return the old one, see [`Archetype::replace`].
+    ///
+    /// Fails, returning the value back, if the id is invalid or replacing is not supported (by default).
+    fn replace_optional(
+        &mut self,
+        id: F::Id,
+        value: Option<Self::Item>,
+    ) -> Result<Option<Self::Item>, Option<Self::Item>> {
+        let _ = id;
+        Err(value)
+    }
+    /// Remove all entities, whether they are present or absent,
+    /// returning an iterator over them together with their id's.
+    fn drain_optional(&mut self) -> Self::DrainOptional;
+    /// Take the entity with a given id out of a drain, whether it is present or absent,
+    /// see [`Storage::drain_take`](crate::storage::Storage::drain_take).
+    fn drain_optional_take(
+        drain: &mut Self::DrainOptional,
+        id: F::Id,
+    ) -> Option<Option<Self::Item>> {
+        let _ = id;
+        drain.next().map(|(_, value)| value)
+    }
 }
 
 /// A type synonym for a specific implementor of [Archetype] for convenient usage in type definitions.
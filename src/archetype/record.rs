@@ -0,0 +1,243 @@
+use super::*;
+
+use crate::storage::StableFamily;
+
+use core::marker::PhantomData;
+
+/// A change of an entity in an archetype, recorded by a [`Recorder`] and replayed by a [`Replica`].
+///
+/// With the `serde` feature, changes can be serialized to be sent over the network.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Change<Id, T> {
+    /// A new entity has been inserted.
+    Inserted(Id, T),
+    /// The entity has been removed.
+    Removed(Id),
+    /// The entity has been modified, holding its new value.
+    Modified(Id, T),
+}
+
+/// A recording layer over an [Archetype], which logs the insertions, removals,
+/// and modifications (through `get_mut`) of the entities as [`Change`]'s.
+/// Modifications that leave the entity unchanged are not recorded.
+///
+/// The archetype can be read through [`Deref`](core::ops::Deref) (e.g. with an immutable [`query!`](crate::query)),
+/// but all changes have to go through the recorder, so that none of them are missed.
+/// Requires `#[split(clone)]` on the archetype to record the values of the entities,
+/// and a [`StableFamily`], since the changes refer to the entities by their id's.
+///
+/// # Example
+///
+/// ```
+/// # use stecs::prelude::*;
+/// # use stecs::archetype::{Change, Recorder, Replica};
+/// # use stecs::storage::arena::Arena;
+/// #[derive(SplitFields, Debug, PartialEq)]
+/// #[split(clone)]
+/// struct Monster {
+///     health: i64,
+/// }
+///
+/// let mut server: Recorder<_, StructOf<Arena<Monster>>> = Recorder::default();
+/// let slime = server.insert(Monster { health: 5 });
+/// let bat = server.insert(Monster { health: 2 });
+/// server.modify(slime, |monster| *monster.health -= 1);
+/// // Not recorded, since the health stays the same
+/// server.modify(bat, |monster| *monster.health = 2);
+/// server.remove(bat);
+///
+/// let changes = server.take_changes();
+/// assert_eq!(changes.len(), 4);
+/// assert_eq!(changes[2], Change::Modified(slime, Monster { health: 4 }));
+///
+/// let mut client: Replica<_, StructOf<Arena<Monster>>> = Replica::default();
+/// let mut changes = changes.into_iter();
+/// client.apply(changes.next().unwrap());
+/// let local = client.local_id(slime).unwrap();
+/// for change in changes {
+///     client.apply(change);
+/// }
+/// // The modification keeps the local id
+/// assert_eq!(client.local_id(slime), Some(local));
+/// assert_eq!(client.get(local).map(|monster| *monster.health), Some(4));
+/// assert_eq!(query!(client, (&health)).count(), 1);
+/// ```
+///
+pub struct Recorder<F: StableFamily, A: Archetype<F>> {
+    archetype: A,
+    changes: Vec<Change<F::Id, A::Item>>,
+    phantom: PhantomData<F>,
+}
+
+impl<F: StableFamily, A: Archetype<F>> Recorder<F, A> {
+    /// Start recording the changes of the archetype.
+    pub fn new(archetype: A) -> Self {
+        Self {
+            archetype,
+            changes: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Stop recording, returning the archetype.
+    pub fn into_inner(self) -> A {
+        self.archetype
+    }
+
+    /// The changes recorded so far.
+    pub fn changes(&self) -> &[Change<F::Id, A::Item>] {
+        &self.changes
+    }
+
+    /// Take the changes recorded so far, e.g. to send them at the end of a tick.
+    pub fn take_changes(&mut self) -> Vec<Change<F::Id, A::Item>> {
+        core::mem::take(&mut self.changes)
+    }
+
+    /// Remove an entity with a given id, recording the removal.
+    pub fn remove(&mut self, id: F::Id) -> Option<A::Item> {
+        let value = self.archetype.remove(id)?;
+        self.changes.push(Change::Removed(id));
+        Some(value)
+    }
+}
+
+impl<F: StableFamily, A: ArchetypeRef<F>> Recorder<F, A>
+where
+    A::Item: StructRef,
+    for<'a> <A::Item as StructRef>::Ref<'a>: Into<A::Item>,
+{
+    /// Insert a new entity, recording the insertion.
+    pub fn insert(&mut self, value: A::Item) -> F::Id {
+        let id = self.archetype.insert(value);
+        let value = self.current(id);
+        self.changes.push(Change::Inserted(id, value));
+        id
+    }
+
+    /// Modify an entity through `get_mut`, recording its new value if it has changed.
+    /// Returns `None` if the entity is absent.
+    pub fn modify<R>(
+        &mut self,
+        id: F::Id,
+        f: impl FnOnce(<A::Item as StructRef>::RefMut<'_>) -> R,
+    ) -> Option<R>
+    where
+        A::Item: PartialEq,
+    {
+        let old = self.archetype.get(id)?.into();
+        let result = f(self
+            .archetype
+            .get_mut(id)
+            .expect("the entity has just been accessed"));
+        let value = self.current(id);
+        if value != old {
+            self.changes.push(Change::Modified(id, value));
+        }
+        Some(result)
+    }
+
+    /// Clone the current value of an entity that has just been changed.
+    fn current(&self, id: F::Id) -> A::Item {
+        self.archetype
+            .get(id)
+            .expect("the entity has just been changed")
+            .into()
+    }
+}
+
+impl<F: StableFamily, A: Archetype<F>> Default for Recorder<F, A> {
+    fn default() -> Self {
+        Self::new(A::default())
+    }
+}
+
+impl<F: StableFamily, A: Archetype<F>> core::ops::Deref for Recorder<F, A> {
+    type Target = A;
+
+    fn deref(&self) -> &Self::Target {
+        &self.archetype
+    }
+}
+
+/// A copy of an [Archetype], kept up to date by replaying the [`Change`]'s recorded by a [`Recorder`].
+///
+/// The replica uses the same storage family as the recorded archetype, but its id's are local,
+/// since the entities are inserted anew (e.g. an `Arena` cannot insert with a chosen id),
+/// and [`Replica::local_id`] maps the id's of the recorded archetype to them.
+/// The family must be a [`StableFamily`] for the map to stay valid.
+/// Modifications are replayed with [`Archetype::replace`], keeping the local id's and the order of the entities.
+///
+/// See [`Recorder`] for an example.
+pub struct Replica<F: StableFamily, A: Archetype<F>> {
+    archetype: A,
+    ids: HashMap<F::Id, F::Id>,
+}
+
+impl<F: StableFamily, A: Archetype<F>> Replica<F, A>
+where
+    F::Id: Eq + Hash,
+{
+    /// Start replaying the changes into the archetype.
+    pub fn new(archetype: A) -> Self {
+        Self {
+            archetype,
+            ids: HashMap::new(),
+        }
+    }
+
+    /// Stop replaying, returning the archetype.
+    pub fn into_inner(self) -> A {
+        self.archetype
+    }
+
+    /// Get the local id of the entity with the given id in the recorded archetype.
+    pub fn local_id(&self, id: F::Id) -> Option<F::Id> {
+        self.ids.get(&id).copied()
+    }
+
+    /// Apply a recorded change.
+    /// Changes of unknown entities are ignored.
+    pub fn apply(&mut self, change: Change<F::Id, A::Item>) {
+        match change {
+            Change::Inserted(id, value) => {
+                let local = self.archetype.insert(value);
+                if let Some(old) = self.ids.insert(id, local) {
+                    self.archetype.remove(old);
+                }
+            }
+            Change::Removed(id) => {
+                if let Some(local) = self.ids.remove(&id) {
+                    self.archetype.remove(local);
+                }
+            }
+            Change::Modified(id, value) => {
+                if let Some(local) = self.ids.get_mut(&id) {
+                    // NOTE: fall back to reinserting if the archetype does not support replacing
+                    if let Err(value) = self.archetype.replace(*local, value) {
+                        self.archetype.remove(*local);
+                        *local = self.archetype.insert(value);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<F: StableFamily, A: Archetype<F>> Default for Replica<F, A>
+where
+    F::Id: Eq + Hash,
+{
+    fn default() -> Self {
+        Self::new(A::default())
+    }
+}
+
+impl<F: StableFamily, A: Archetype<F>> core::ops::Deref for Replica<F, A> {
+    type Target = A;
+
+    fn deref(&self) -> &Self::Target {
+        &self.archetype
+    }
+}
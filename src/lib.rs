@@ -125,6 +125,23 @@
 //! The storage and component parts are separated by a `.Get` indicating access to a specific entity's component inside a storage, but can be omitted when not using the component part.
 //!
 //! **Note**: there's a special optic `id` (without `&`) that returns the id of the entity being queried.
+//! If the archetype has a field called `id`, it can be accessed with a raw identifier: `&r#id`.
+//!
+//! ```
+//! # use stecs::prelude::*;
+//! #[derive(SplitFields)]
+//! struct Peer {
+//!     id: u32,
+//!     ping: f32,
+//! }
+//!
+//! let mut peers: StructOf<Vec<Peer>> = Default::default();
+//! peers.insert(Peer { id: 7, ping: 0.1 });
+//!
+//! for (entity, peer_id, ping) in query!(peers, (id, &r#id, &ping)) {
+//!     println!("[{entity}] peer {peer_id} has ping {ping}");
+//! }
+//! ```
 //!
 //! Take, for example, `&mut body.health.Get.Some`.
 //!
@@ -133,15 +150,157 @@
 //! 2. `body.health`. The **storage** optic provides the path to the component storage.
 //!    It is usually a single identifier that is the name of the component.
 //!    But it can also be multiple dot-separated identifiers when querying inside a nested storage.
+//!    Inside an optional nested storage, the entities without it are skipped: `shield.durability`,
+//!    which can also be marked explicitly with a `.Some`: `shield.Some.durability`.
 //!
 //! 3. `.Some`. The **component** optic describes manipulations on the component value and starts after the `.Get`.
 //!    Typically, the component optic is either omitted or used to filter out optional components: `.Some`.
 //!
-//! In general, there are 3 things you can do in an optic:
+//! In general, there are 4 things you can do in an optic:
 //! - access a field, like in normal Rust: `position.x`
 //! - get the component from the storage: `.Get`
-//! - filter out optional components: `.Some`
+//! - filter out optional components or optional nested storages: `.Some`
+//! - filter out `bool` components (e.g. tags) that are `false`: `.True`, like `&selected.Get.True`
+//!
+//! An optic that may filter out entities (using `.Some`, `.True`, or a join) is called a prism.
+//! A query without prisms from a single archetype yields exactly one item per entity,
+//! so it is an [`ExactSizeIterator`] and a [`DoubleEndedIterator`] (as long as the storage's id's are).
+//! Components from different nested storages are filtered as well, since those may be optional.
+//!
+//! ```
+//! # use stecs::prelude::*;
+//! #[derive(SplitFields)]
+//! struct Unit {
+//!     position: f32,
+//!     health: Option<f32>,
+//! }
+//!
+//! let mut units: StructOf<Vec<Unit>> = Default::default();
+//! units.insert(Unit { position: 0.0, health: Some(5.0) });
+//! units.insert(Unit { position: 1.0, health: None });
+//!
+//! assert_eq!(query!(units, (&position)).len(), 2);
+//! let last = query!(units, (id, &mut position)).rev().next();
+//! assert_eq!(last.map(|(id, _)| id), Some(1));
+//!
+//! // Prisms filter entities, so the size is not known in advance
+//! assert_eq!(query!(units, (&health.Get.Some)).count(), 1);
+//! ```
+//!
+//! # Joins
+//!
+//! An immutable optic that results in an id can be followed by `->` and a path to another storage,
+//! to get the component of the entity referred to by that id.
+//! Entities whose id's are dangling (not present in the target storage) are skipped.
+//! Joins can be chained: `&leader -> world.units.leader -> world.units.position`.
+//!
+//! ```
+//! # use stecs::prelude::*;
+//! # use stecs::storage::arena::{Arena, ArenaId};
+//! #[derive(SplitFields)]
+//! struct Unit {
+//!     position: f32,
+//!     target: Option<ArenaId>,
+//! }
+//!
+//! let mut units: StructOf<Arena<Unit>> = Default::default();
+//! let a = units.insert(Unit { position: 1.0, target: None });
+//! units.insert(Unit { position: 2.0, target: Some(a) });
 //!
+//! for (position, target_position) in query!(units, (&position, &target.Get.Some -> units.position)) {
+//!     println!("unit at {position} is targeting a unit at {target_position}");
+//! }
+//! ```
+//!
+//! # Replication
+//!
+//! A [`Recorder`](archetype::Recorder) logs the changes of an archetype, which can be replayed by a [`Replica`](archetype::Replica),
+//! e.g. on the clients of a multiplayer server.
+//! With the `serde` feature, the [`Change`](archetype::Change)'s (and the id's of the `Arena`, `HashStorage` and `BTreeStorage`) can be serialized.
+//!
+//! # `no_std`
+//!
+//! The library only depends on `core` and `alloc`, so it can be used in `no_std` environments with an allocator.
+//! The default `std` feature only enables the `std` support of the dependencies,
+//! so disable the default features (keeping the ones you need, e.g. `arena`) to build without `std`.
+//! The maps of id's (e.g. returned by [`Archetype::compact`](archetype::Archetype::compact))
+//! and [`HashStorage`](storage::hashstorage::HashStorage) use [`hashbrown`].
+//!
+
+#![no_std]
+
+extern crate alloc;
+
+pub use hashbrown;
+
+/// Re-exports used by the generated code, so that it does not rely on `std` being available.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::archetype::debug::debug_table;
+    pub use alloc::{format, vec, vec::Vec};
+
+    use crate::storage::Storage;
+
+    /// The [`Storage`] methods used by the macros.
+    pub trait StorageExt<T>: Storage<T> {
+        /// Get a component in a query, which calls [`Storage::get`] for most storages.
+        ///
+        /// Other fields in the archetypes (e.g. `BitsetStorage` or nested archetypes)
+        /// provide an inherent method with the same name, which takes precedence.
+        fn query_get(&self, id: Self::Id) -> Option<&T> {
+            self.get(id)
+        }
+
+        /// Get a component in a mutable query, see [`StorageExt::query_get`].
+        fn query_get_mut(&mut self, id: Self::Id) -> Option<&mut T> {
+            self.get_mut(id)
+        }
+
+        /// Get many components in a mutable query, see [`StorageExt::query_get`].
+        ///
+        /// # Safety
+        /// See [`Storage::get_many_unchecked_mut`].
+        ///
+        #[cfg(feature = "query_mut")]
+        unsafe fn query_get_many_unchecked_mut<'a, I: Iterator<Item = Self::Id>>(
+            &'a mut self,
+            ids: I,
+        ) -> crate::storage::GetManyUncheckedMut<'a, T, Self, I>
+        where
+            T: 'a,
+        {
+            // SAFETY: the requirements are the same
+            unsafe { self.get_many_unchecked_mut(ids) }
+        }
+
+        /// Collect the valid id's out of the given ones, skipping the duplicates and preserving the order.
+        ///
+        /// The resulting id's satisfy the safety requirements of [`Storage::get_many_unchecked_mut`].
+        fn valid_unique_ids(&self, ids: impl IntoIterator<Item = Self::Id>) -> Vec<Self::Id>
+        where
+            Self::Id: Eq + core::hash::Hash,
+        {
+            let mut seen = hashbrown::HashSet::new();
+            ids.into_iter()
+                .filter(|&id| Storage::get(self, id).is_some() && seen.insert(id))
+                .collect()
+        }
+
+        /// Check that all of the given id's are valid and distinct, without allocating.
+        ///
+        /// If so, they satisfy the safety requirements of [`Storage::get_many_unchecked_mut`].
+        fn are_valid_unique(&self, ids: &[Self::Id]) -> bool
+        where
+            Self::Id: PartialEq,
+        {
+            ids.iter()
+                .enumerate()
+                .all(|(i, &id)| Storage::get(self, id).is_some() && !ids[..i].contains(&id))
+        }
+    }
+
+    impl<T, S: Storage<T>> StorageExt<T> for S {}
+}
 
 /// Derive macro for the static archetypes.
 ///
@@ -150,11 +309,22 @@
 /// - `XStructOf`, an analogous structure to the one being derived, with fields being general storages (see example below)
 /// - `Ref` struct that is used when iterating over the generated archetype
 /// - `RefMut` struct that is used when mutably iterating over the generated archetype
+/// - impl [`HasField`](crate::archetype::HasField) for each field of `XStructOf`, to access fields in generic functions
+/// - conversions between `XStructOf` and `Vec<X>`: `From`, [`FromIterator`], and `to_vec` (with `#[split(clone)]`)
 ///
-/// You can annotate the struct with `#[split(debug)]` to derive a [`Debug`](trait@std::fmt::Debug) impl
-/// for the `Ref` and `RefMut` structs, and with `#[split(clone)]` to derive [`Clone`](trait@std::clone::Clone).
+/// You can annotate the struct with `#[split(debug)]` to derive a [`Debug`](trait@core::fmt::Debug) impl
+/// for the `Ref` and `RefMut` structs, and with `#[split(clone)]` to derive [`Clone`](trait@core::clone::Clone).
+/// With `#[split(debug)]`, `XStructOf` also implements [`Debug`](trait@core::fmt::Debug) by printing the entities as a table,
+/// so a manual `Debug` impl for `XStructOf` conflicts with the flag.
+/// With `#[split(eq)]`, the `Ref` and `RefMut` structs derive [`PartialEq`], and `XStructOf` implements [`PartialEq`]
+/// by comparing the entities with the same id's, regardless of the internal layout of the storages.
+/// With `#[split(hash)]`, they derive and implement [`Hash`](trait@core::hash::Hash) consistently with that,
+/// by hashing the entities sorted by their id's (collecting the id's into a `Vec`).
 ///
 /// Also, you can annotate fields with `#[split(nested)]`, if that field is another archetype, to also split its fields.
+/// A nested field may also be optional, i.e. `Option<T>`, in which case its fields are stored in an
+/// [`OptionFamily`](storage::option::OptionFamily), and queries skip the entities where it is absent,
+/// e.g. `&shield.durability` (or, explicitly, `&shield.Some.durability`).
 ///
 /// # Example
 ///
@@ -174,10 +344,330 @@
 ///     position: Position,
 ///     lifetime: f64,
 /// }
+///
+/// #[derive(SplitFields)]
+/// struct Shield {
+///     durability: f64,
+/// }
+///
+/// #[derive(SplitFields)]
+/// struct Unit {
+///     #[split(nested)]
+///     position: Position,
+///     #[split(nested)]
+///     shield: Option<Shield>,
+/// }
+///
+/// let mut units: StructOf<Vec<Unit>> = Default::default();
+/// units.insert(Unit {
+///     position: Position { x: 0.0, y: 0.0 },
+///     shield: None,
+/// });
+/// units.insert(Unit {
+///     position: Position { x: 1.0, y: 0.0 },
+///     shield: Some(Shield { durability: 5.0 }),
+/// });
+///
+/// // Only the units that have a shield
+/// for (x, durability) in query!(units, (&position.x, &mut shield.durability)) {
+///     *durability -= 1.0;
+/// }
+/// assert_eq!(query!(units, (&shield.Some.durability)).count(), 1);
+/// ```
+///
+/// # Optional components
+///
+/// A field of type `Option<T>` annotated with `#[split(optional)]` is stored in a
+/// [`BitsetStorage`](storage::bitset::BitsetStorage), which keeps the components densely
+/// together with a bitset of which of them are present.
+/// Such components are accessed with a `.Some` in the storage optic, e.g. `&damage.Some`,
+/// and queries then iterate only over the entities where they are present, skipping the absent ones a word at a time.
+/// Without it, e.g. `&damage` or `&damage.Get.Some`, they are accessed like an `Option<T>` component, as `Option<&T>`.
+/// The `Ref` and `RefMut` structs hold them as `Option<&T>` and `Option<&mut T>`.
+///
+/// Similarly, a `bool` field annotated with `#[split(tag)]` is stored as just a bitset in a
+/// [`TagStorage`](storage::bitset::TagStorage), which can set or clear the tags of many entities at once.
+/// Tags are filtered with `&selected.Get.True`, and since they are bits, the `RefMut` struct holds them as a
+/// [`TagMut`](storage::bitset::TagMut), which writes the tag back when dropped.
+///
+/// Both require a [`DenseFamily`](storage::DenseFamily) (e.g. `Vec`), and such a struct cannot itself be nested.
+///
+/// ```
+/// # use stecs::prelude::*;
+/// #[derive(SplitFields)]
+/// struct Monster {
+///     health: f32,
+///     #[split(optional)]
+///     damage: Option<f32>,
+/// }
+///
+/// let mut monsters: StructOf<Vec<Monster>> = Default::default();
+/// monsters.insert(Monster { health: 5.0, damage: None });
+/// let id = monsters.insert(Monster { health: 3.0, damage: Some(1.5) });
+///
+/// for (health, damage) in query!(monsters, (&mut health, &damage.Some)) {
+///     *health -= *damage;
+/// }
+/// assert_eq!(monsters.get(id).unwrap().damage, Some(&1.5));
+/// assert_eq!(query!(monsters, (&health)).copied().collect::<Vec<_>>(), [5.0, 1.5]);
+/// assert_eq!(query!(monsters, (&damage)).collect::<Vec<_>>(), [None, Some(&1.5)]);
+/// ```
+///
+/// # Sorting
+///
+/// `XStructOf` can be sorted with `sort_by` and `sort_by_key`, which permute all columns consistently.
+/// Since the id's may change (e.g. they are the indices for `Vec`), the map from old to new id's is returned.
+///
+/// ```
+/// # use stecs::prelude::*;
+/// #[derive(SplitFields)]
+/// struct Sprite {
+///     depth: i32,
+///     parent: Option<usize>,
+/// }
+///
+/// let mut sprites: StructOf<Vec<Sprite>> = Default::default();
+/// let background = sprites.insert(Sprite { depth: 0, parent: None });
+/// sprites.insert(Sprite { depth: 2, parent: Some(background) });
+/// sprites.insert(Sprite { depth: -1, parent: None });
+///
+/// let remap = sprites.sort_by_key(|sprite| *sprite.depth);
+/// for parent in query!(sprites, (&mut parent.Get.Some)) {
+///     *parent = remap[parent];
+/// }
+/// assert_eq!(sprites.depth, [-1, 0, 2]);
+/// assert_eq!(sprites.parent[2], Some(1));
+/// ```
+///
+/// # Debug, equality and hashing
+///
+/// ```
+/// # use stecs::prelude::*;
+/// # use std::hash::{BuildHasher, RandomState};
+/// #[derive(SplitFields)]
+/// #[split(debug, eq, hash)]
+/// struct Unit {
+///     health: i64,
+///     name: &'static str,
+/// }
+///
+/// let mut units: StructOf<Vec<Unit>> = Default::default();
+/// units.insert(Unit { health: 5, name: "knight" });
+/// units.insert(Unit { health: 10, name: "archer" });
+///
+/// assert_eq!(
+///     format!("{units:?}"),
+///     "id | health | name
+/// ---+--------+---------
+/// 0  | 5      | \"knight\"
+/// 1  | 10     | \"archer\""
+/// );
+///
+/// let mut other: StructOf<Vec<Unit>> = Default::default();
+/// other.insert(Unit { health: 5, name: "knight" });
+/// assert_ne!(units, other);
+/// other.insert(Unit { health: 10, name: "archer" });
+/// assert_eq!(units, other);
+///
+/// let state = RandomState::new();
+/// assert_eq!(state.hash_one(&units), state.hash_one(&other));
+/// ```
+///
+/// # Hooks
+///
+/// With `#[split(on_insert = path, on_remove = path)]`, the given functions are called with the id and the `Ref`
+/// of an entity right after it is inserted (by `insert`, `try_insert`, or `insert_with_id`), and right before it is removed.
+/// Since `XStructOf` is generic over the storage family, the hooks should be generic over the id type.
+/// Draining calls `on_remove` for all entities, while consuming conversions (`into_iter` and `Vec::from`) do not.
+/// Cloning (e.g. `to_vec`) does not call the hooks either, while `replace` and reordering (e.g. sorting or compacting)
+/// call `on_remove` with the old id's and then `on_insert` with the new ones.
+/// Modifications through `get_mut` or queries do not call the hooks.
+///
+/// Note that removing from a [`DenseFamily`](storage::DenseFamily) (e.g. `Vec`) moves the last entity
+/// into the place of the removed one, changing its id without calling the hooks.
+/// Use a [`StableFamily`](storage::StableFamily) (e.g. `Arena`) if the hooks keep track of the entities by their id's.
+///
+/// ```
+/// # use stecs::prelude::*;
+/// use std::sync::atomic::{AtomicI64, Ordering};
+///
+/// static TOTAL_HEALTH: AtomicI64 = AtomicI64::new(0);
+///
+/// fn spawned<Id>(_id: Id, unit: UnitRef<'_>) {
+///     TOTAL_HEALTH.fetch_add(*unit.health, Ordering::Relaxed);
+/// }
+///
+/// fn despawned<Id>(_id: Id, unit: UnitRef<'_>) {
+///     TOTAL_HEALTH.fetch_sub(*unit.health, Ordering::Relaxed);
+/// }
+///
+/// #[derive(SplitFields)]
+/// #[split(clone, on_insert = spawned, on_remove = despawned)]
+/// struct Unit {
+///     health: i64,
+/// }
+///
+/// let mut units: StructOf<Vec<Unit>> = Default::default();
+/// let knight = units.insert(Unit { health: 5 });
+/// units.insert(Unit { health: 3 });
+/// assert_eq!(TOTAL_HEALTH.load(Ordering::Relaxed), 8);
+///
+/// units.remove(knight);
+/// assert_eq!(TOTAL_HEALTH.load(Ordering::Relaxed), 3);
+///
+/// // Sorting reinserts the entities
+/// units.sort_by_key(|unit| *unit.health);
+/// assert_eq!(TOTAL_HEALTH.load(Ordering::Relaxed), 3);
+///
+/// let cloned = units.to_vec();
+/// assert_eq!(cloned.len(), 1);
+/// assert_eq!(TOTAL_HEALTH.load(Ordering::Relaxed), 3);
+///
+/// units.drain();
+/// assert_eq!(TOTAL_HEALTH.load(Ordering::Relaxed), 0);
+///
+/// // Consuming the archetype moves the entities out without removing them
+/// units.insert(Unit { health: 2 });
+/// let units: Vec<Unit> = units.into();
+/// assert_eq!(units.len(), 1);
+/// assert_eq!(TOTAL_HEALTH.load(Ordering::Relaxed), 2);
+/// ```
+///
+/// # Diff and patch
+///
+/// With `#[split(diff)]`, `XStructOf::diff(&old, &new)` compares two states of the archetype by the id's of the entities,
+/// returning an `XPatch` with the added and removed entities, and the changed fields (as `XChanges`) of the others.
+/// The patch can be applied to the old state with `apply`, which keeps the id's, so the family has to be a
+/// [`StableFamily`](storage::StableFamily) and support [`insert_with_id`](crate::storage::Storage::insert_with_id)
+/// (e.g. `BTreeStorage` or `HashStorage`).
+/// Swapping the arguments, `diff(&new, &old)` gives the patch to undo the changes.
+/// Nested fields are not supported.
+///
+/// ```
+/// # use stecs::prelude::*;
+/// # use stecs::storage::btree::BTreeStorage;
+/// #[derive(SplitFields, Debug, Clone, PartialEq)]
+/// #[split(clone, diff)]
+/// struct Unit {
+///     health: i64,
+///     name: &'static str,
+/// }
+///
+/// let mut units: StructOf<BTreeStorage<Unit>> = Default::default();
+/// let knight = units.insert(Unit { health: 5, name: "knight" });
+/// let archer = units.insert(Unit { health: 3, name: "archer" });
+/// let old = units.clone();
+///
+/// *units.get_mut(knight).unwrap().health -= 1;
+/// units.remove(archer);
+/// let mage = units.insert(Unit { health: 2, name: "mage" });
+///
+/// let patch = UnitStructOf::diff(&old, &units);
+/// assert_eq!(patch.added, [(mage, Unit { health: 2, name: "mage" })]);
+/// assert_eq!(patch.removed, [archer]);
+/// assert_eq!(patch.changed, [(knight, UnitChanges { health: Some(4), name: None })]);
+///
+/// let mut replay = old.clone();
+/// replay.apply(patch).unwrap();
+/// assert_eq!(replay.get(mage).map(|unit| *unit.name), Some("mage"));
+///
+/// // Undo the changes
+/// replay.apply(UnitStructOf::diff(&units, &old)).unwrap();
+/// assert_eq!(replay.get(archer).map(|unit| *unit.health), Some(3));
+/// assert_eq!(replay.get(knight).map(|unit| *unit.health), Some(5));
+///
+/// // The id's stay valid after removals
+/// let rogue = units.insert(Unit { health: 4, name: "rogue" });
+/// let old = units.clone();
+/// units.remove(knight);
+/// units.remove(rogue);
+/// *units.get_mut(mage).unwrap().health = 1;
+/// let mut replay = old.clone();
+/// replay.apply(UnitStructOf::diff(&old, &units)).unwrap();
+/// assert_eq!(
+///     query!(replay, (id, &health, &name)).collect::<Vec<_>>(),
+///     [(mage, &1, &"mage")],
+/// );
+/// ```
+///
+/// Dense families (e.g. `Vec`) move the entities on removal, so they cannot be patched:
+///
+/// ```compile_fail
+/// # use stecs::prelude::*;
+/// #[derive(SplitFields, Debug, Clone, PartialEq)]
+/// #[split(clone, diff)]
+/// struct Unit {
+///     health: i64,
+/// }
+///
+/// let units: StructOf<Vec<Unit>> = Default::default();
+/// let patch = UnitStructOf::diff(&units, &units);
+/// ```
+///
+/// # Conversions
+///
+/// ```
+/// # use stecs::prelude::*;
+/// #[derive(SplitFields, Clone, Debug, PartialEq)]
+/// #[split(clone)]
+/// struct Unit {
+///     health: i64,
+///     name: String,
+/// }
+///
+/// let loaded = vec![
+///     Unit { health: 5, name: "knight".to_string() },
+///     Unit { health: 3, name: "archer".to_string() },
+/// ];
+/// let units: StructOf<Vec<Unit>> = loaded.clone().into();
+/// assert_eq!(units.name[1], "archer");
+///
+/// assert_eq!(units.to_vec(), loaded);
+/// let units: Vec<Unit> = units.into();
+/// assert_eq!(units, loaded);
 /// ```
 ///
 pub use stecs_derive::SplitFields;
 
+/// Derive macro for reusable query views.
+///
+/// Generates a `query` function (or `query_mut`, if any of the fields is a mutable reference)
+/// that queries the view from the archetype given by `#[query(archetype = "...")]`.
+/// The view must have a lifetime parameter, which is used for the borrow of the archetype.
+///
+/// By default, each field accesses the component of the same name (and `id` is the id of the entity),
+/// same as the shorthand syntax in [`query!`].
+/// Otherwise, an [optic](crate#optics) can be specified with `#[query(optic = "...")]`.
+///
+/// # Example
+///
+/// ```
+/// # use stecs::prelude::*;
+/// #[derive(SplitFields)]
+/// struct Unit {
+///     pos: (f32, f32),
+///     damage: Option<f32>,
+/// }
+///
+/// #[derive(Query)]
+/// #[query(archetype = "StructOf<Vec<Unit>>")]
+/// struct UnitView<'a> {
+///     id: usize,
+///     pos: &'a (f32, f32),
+///     #[query(optic = "damage.Get.Some")]
+///     damage: &'a mut f32,
+/// }
+///
+/// let mut units: StructOf<Vec<Unit>> = Default::default();
+/// units.insert(Unit { pos: (0.0, 0.0), damage: Some(1.0) });
+///
+/// for unit in UnitView::query_mut(&mut units) {
+///     *unit.damage *= 2.0;
+/// }
+/// ```
+///
+pub use stecs_derive::Query;
+
 /// Get components of a specific entity.
 ///
 /// Syntax is identical to [`query!`], with an additional `id` argument right after the archetype.
@@ -220,6 +710,36 @@ pub use stecs_derive::SplitFields;
 ///
 pub use stecs_derive::storage_get as get;
 
+/// Get components of multiple distinct entities at the same time.
+///
+/// Syntax is identical to [`get!`], except that the id's are given as an array.
+/// Returns `None` if any of the id's is invalid, or if they are not distinct,
+/// which allows accessing components of different entities mutably at the same time.
+/// Also returns `None` if any of the entities is filtered out by a prism, e.g. its component accessed with `.Some` is absent.
+///
+/// # Example
+///
+/// ```
+/// # use stecs::prelude::*;
+/// #
+/// # #[derive(SplitFields)]
+/// # struct Unit {
+/// #     health: i64,
+/// #     damage: i64,
+/// # }
+/// #
+/// # let mut units: StructOf<Vec<Unit>> = Default::default();
+/// let a = units.insert(Unit { health: 10, damage: 3 });
+/// let b = units.insert(Unit { health: 10, damage: 5 });
+///
+/// if let Some([(attacker, _), (_, target)]) = get_many!(units, [a, b], (&damage, &mut health)) {
+///     *target -= *attacker;
+/// }
+/// assert!(get_many!(units, [a, a], (&mut health)).is_none());
+/// ```
+///
+pub use stecs_derive::storage_get_many as get_many;
+
 /// Query components from archetypes.
 ///
 /// The general syntax for a query is: `query!(<archetype>, <view>)`.
@@ -264,6 +784,27 @@ pub use stecs_derive::storage_get as get;
 /// Multiple archetypes can be queried at the same time, if the target views match all archetypes,
 /// by listing them in an array: `query!([world.projectiles, world.monsters], <view>)`.
 ///
+/// To query only a subset of entities, follow the archetype with `where` and an iterator of their id's: `query!(world.units where ids, <view>)`.
+/// Invalid and duplicate id's are skipped, so mutable access stays sound.
+///
+/// ```
+/// # use stecs::prelude::*;
+/// # #[derive(SplitFields)]
+/// # struct Unit {
+/// #     health: i64,
+/// # }
+/// # let mut units: StructOf<Vec<Unit>> = Default::default();
+/// # for _ in 0..5 { units.insert(Unit { health: 5 }); }
+/// let selection = vec![1, 3, 3];
+/// for health in query!(units where selection.iter().copied(), (&mut health)) {
+///     *health -= 1;
+/// }
+/// ```
+///
+/// In functions generic over the archetype, prefix a reference to it with `impl`: `query!(impl archetype, <view>)`.
+/// The fields are then accessed through [`HasField`](archetype::HasField), including the `ids` column,
+/// see [`Fields`](archetype::Fields) for an example.
+///
 /// ## View
 /// Queried components can be viewed in either tuple or struct form.
 /// In either case, the syntax is the same as normal struct/tuple construction,
@@ -271,6 +812,51 @@ pub use stecs_derive::storage_get as get;
 ///
 pub use stecs_derive::storage_query as query;
 
+/// Access whole component storages as slices, for archetypes with contiguous storages (e.g. `StructOf<Vec<_>>`).
+///
+/// Syntax is identical to [`query!`], but each optic must refer to a whole storage (without `.Get` or `.Some`),
+/// which must implement [`SliceStorage`](storage::SliceStorage).
+/// Instead of an iterator, returns a single view of slices,
+/// where the components of an entity are at the index equal to its id.
+/// This allows passing the columns directly to SIMD or autovectorizable code.
+///
+/// # Example
+///
+/// ```
+/// # use stecs::prelude::*;
+/// #[derive(SplitFields)]
+/// struct Unit {
+///     #[split(nested)]
+///     position: Position,
+///     velocity: f32,
+/// }
+///
+/// #[derive(SplitFields)]
+/// struct Position {
+///     x: f32,
+///     y: f32,
+/// }
+///
+/// let mut units: StructOf<Vec<Unit>> = Default::default();
+/// units.insert(Unit { position: Position { x: 0.0, y: 0.0 }, velocity: 1.0 });
+/// units.insert(Unit { position: Position { x: 5.0, y: 0.0 }, velocity: 2.0 });
+///
+/// let (xs, velocities) = query_slices!(units, (&mut position.x, &velocity));
+/// for (x, vel) in xs.iter_mut().zip(velocities) {
+///     *x += *vel;
+/// }
+/// assert_eq!(units.position.x, [1.0, 7.0]);
+/// ```
+///
+pub use stecs_derive::storage_query_slices as query_slices;
+
+/// The type-level name of a field, used with [`HasField`](archetype::HasField)
+/// to write functions generic over archetypes.
+///
+/// For example, `field!(position)` refers to a field named `position`.
+///
+pub use stecs_derive::field_name as field;
+
 /// The traits for describing archetypes and split storages.
 pub mod archetype;
 /// The [`Storage`](storage::Storage) trait and basic implementors.
@@ -279,9 +865,9 @@ pub mod storage;
 /// use `stecs::prelude::*;` to import all necessary traits, types, and macros.
 pub mod prelude {
     pub use crate::{
-        archetype::{Archetype, SplitFields, StructOf, StructOfAble as _},
-        get, query,
+        archetype::{Archetype, HasField, SplitFields, StructOf, StructOfAble as _},
+        field, get, get_many, query, query_slices,
         storage::{Storage, StorageFamily},
-        SplitFields,
+        Query, SplitFields,
     };
 }
@@ -1,6 +1,6 @@
 use crate::{
     archetype::{SplitFields, StructOfAble},
-    storage::{Storage, StorageFamily},
+    storage::{StableFamily, Storage, StorageFamily},
 };
 
 pub use slotmap::{self, DefaultKey as ArenaId, SlotMap};
@@ -9,17 +9,27 @@ pub use slotmap::{self, DefaultKey as ArenaId, SlotMap};
 pub type Arena<T> = SlotMap<ArenaId, T>;
 
 /// Family of [`SlotMap<K, V>`] storages.
-pub struct SlotMapFamily<K: slotmap::Key>(std::marker::PhantomData<K>);
+pub struct SlotMapFamily<K: slotmap::Key>(core::marker::PhantomData<K>);
 
 impl<K: slotmap::Key> StorageFamily for SlotMapFamily<K> {
     type Id = K;
     type Storage<T> = SlotMap<K, T>;
 }
 
+impl<K: slotmap::Key> StableFamily for SlotMapFamily<K> {}
+
+// NOTE: `SlotMap` does not support inserting with a chosen key,
+// so `insert_with_id` always fails
 unsafe impl<K: slotmap::Key, T> Storage<T> for SlotMap<K, T> {
     type Family = SlotMapFamily<K>;
     type Id = K;
-    fn ids(&self) -> impl Iterator<Item = Self::Id> + Clone {
+    type IdsIter<'a>
+        = slotmap::basic::Keys<'a, K, T>
+    where
+        K: 'a,
+        T: 'a;
+    type Drain = slotmap::basic::IntoIter<K, T>;
+    fn ids(&self) -> Self::IdsIter<'_> {
         // SAFETY: `keys()` guarantees validity and uniqueness
         self.keys()
     }
@@ -35,18 +45,9 @@ unsafe impl<K: slotmap::Key, T> Storage<T> for SlotMap<K, T> {
     fn remove(&mut self, id: Self::Id) -> Option<T> {
         self.remove(id)
     }
-    #[cfg(feature = "query_mut")]
-    unsafe fn get_many_unchecked_mut<'a>(
-        &'a mut self,
-        ids: impl Iterator<Item = Self::Id>,
-    ) -> impl Iterator<Item = &'a mut T>
-    where
-        T: 'a,
-    {
-        ids.map(move |i| {
-            let r = self.get_mut(i).expect("invalid id: entry absent");
-            &mut *(r as *mut T)
-        })
+    fn drain(&mut self) -> Self::Drain {
+        // NOTE: iterates in the order of the slots, which is the same for all columns
+        core::mem::take(self).into_iter()
     }
 }
 
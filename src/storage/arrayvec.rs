@@ -0,0 +1,113 @@
+use crate::{
+    archetype::{SplitFields, StructOfAble},
+    storage::{reorder_dense, DenseFamily, InsertError, SliceStorage, Storage, StorageFamily},
+};
+
+use alloc::vec::Vec;
+
+pub use arrayvec::{self, ArrayVec};
+
+/// Family of [`ArrayVec<T, N>`] storages, which keep at most `N` components inline, without heap allocations.
+///
+/// The id's are indices, same as for [`Vec`]. Inserting into a full storage panics,
+/// use [`Storage::try_insert`] or [`Archetype::try_insert`](crate::archetype::Archetype::try_insert) to handle it instead.
+/// Useful for small bounded archetypes, e.g. player slots.
+///
+/// # Example
+///
+/// ```
+/// # use stecs::prelude::*;
+/// # use stecs::storage::{arrayvec::ArrayVec, InsertError};
+/// #[derive(SplitFields, Debug, PartialEq)]
+/// struct Player {
+///     score: u32,
+/// }
+///
+/// let mut players: StructOf<ArrayVec<Player, 2>> = Default::default();
+/// assert_eq!(players.try_insert(Player { score: 0 }), Ok(0));
+/// assert_eq!(players.try_insert(Player { score: 0 }), Ok(1));
+/// assert_eq!(
+///     players.try_insert(Player { score: 5 }),
+///     Err(InsertError::CapacityExceeded(Player { score: 5 }))
+/// );
+///
+/// // NOTE: `ArrayVec` has an inherent `try_insert` at an index, so call the trait method explicitly
+/// let mut scores: ArrayVec<u32, 1> = Default::default();
+/// assert_eq!(Storage::try_insert(&mut scores, 10), Ok(0));
+/// assert_eq!(
+///     Storage::try_insert(&mut scores, 20),
+///     Err(InsertError::CapacityExceeded(20))
+/// );
+/// ```
+///
+pub struct ArrayVecFamily<const N: usize>;
+
+impl<const N: usize> StorageFamily for ArrayVecFamily<N> {
+    type Id = usize;
+    type Storage<T> = ArrayVec<T, N>;
+}
+
+unsafe impl<T, const N: usize> Storage<T> for ArrayVec<T, N> {
+    type Family = ArrayVecFamily<N>;
+    type Id = usize;
+    type IdsIter<'a>
+        = core::ops::Range<usize>
+    where
+        T: 'a;
+    type Drain = core::iter::Enumerate<arrayvec::IntoIter<T, N>>;
+    fn ids(&self) -> Self::IdsIter<'_> {
+        // SAFETY: guaranteed validity and uniqueness
+        0..self.len()
+    }
+    fn insert(&mut self, value: T) -> Self::Id {
+        let id = self.len();
+        self.push(value);
+        id
+    }
+    fn try_insert(&mut self, value: T) -> Result<Self::Id, InsertError<T>> {
+        let id = self.len();
+        self.try_push(value)
+            .map_err(|err| InsertError::CapacityExceeded(err.element()))?;
+        Ok(id)
+    }
+    fn insert_with_id(&mut self, id: Self::Id, value: T) -> Result<(), T> {
+        // NOTE: the id's are indices, so only the next one is available
+        if id != self.len() {
+            return Err(value);
+        }
+        self.try_push(value).map_err(|err| err.element())
+    }
+    fn get(&self, id: Self::Id) -> Option<&T> {
+        self.as_slice().get(id)
+    }
+    fn get_mut(&mut self, id: Self::Id) -> Option<&mut T> {
+        self.as_mut_slice().get_mut(id)
+    }
+    fn remove(&mut self, id: Self::Id) -> Option<T> {
+        self.swap_pop(id)
+    }
+    fn drain(&mut self) -> Self::Drain {
+        // NOTE: the id's are indices, so they are yielded in order
+        core::mem::take(self).into_iter().enumerate()
+    }
+    fn reorder(&mut self, order: &[Self::Id]) -> Vec<Self::Id> {
+        reorder_dense(self, order)
+    }
+}
+
+impl<T, const N: usize> SliceStorage<T> for ArrayVec<T, N> {
+    fn as_slice(&self) -> &[T] {
+        self
+    }
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+// SAFETY: the id's are indices, and removal uses `swap_pop`
+unsafe impl<const N: usize> DenseFamily for ArrayVecFamily<N> {}
+
+impl<T: SplitFields<ArrayVecFamily<N>>, const N: usize> StructOfAble for ArrayVec<T, N> {
+    type Struct = T;
+    type Family = ArrayVecFamily<N>;
+}
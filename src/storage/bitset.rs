@@ -0,0 +1,755 @@
+// NOTE: `usize::is_multiple_of` requires Rust 1.87
+#![allow(clippy::manual_is_multiple_of)]
+
+#[cfg(feature = "query_mut")]
+use crate::storage::GetManyOptionalUncheckedMut;
+use crate::storage::{option::present, DenseFamily, InsertError, Storage, StorageFamily};
+
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// A storage of optional components, which keeps them densely inside another [`DenseFamily`],
+/// and tracks whether they are present in a bitset.
+///
+/// Used for `#[split(optional)]` fields of type `Option<T>`. Compared to storing `Option<T>`,
+/// it does not need the space for the discriminant, and iterating over the present components
+/// (e.g. `.Some` in a query) skips the absent ones a whole word of the bitset at a time.
+pub struct BitsetStorage<F: DenseFamily, T> {
+    /// The components, which are initialized only if the corresponding bit is set.
+    values: F::Storage<MaybeUninit<T>>,
+    /// The presence bits, one per entity, set to zero past `len`.
+    present: Vec<u64>,
+    len: usize,
+}
+
+impl<F: DenseFamily, T> BitsetStorage<F, T> {
+    fn is_present(&self, id: usize) -> bool {
+        id < self.len && self.present[id / WORD_BITS] & (1 << (id % WORD_BITS)) != 0
+    }
+
+    fn set_present(&mut self, id: usize, value: bool) {
+        let mask = 1 << (id % WORD_BITS);
+        if value {
+            self.present[id / WORD_BITS] |= mask;
+        } else {
+            self.present[id / WORD_BITS] &= !mask;
+        }
+    }
+
+    /// Push the presence bit of a newly inserted entity.
+    fn push(&mut self, id: usize, value: bool) {
+        debug_assert_eq!(id, self.len, "dense families must insert at the end");
+        if self.len % WORD_BITS == 0 {
+            self.present.push(0);
+        }
+        self.len += 1;
+        self.set_present(id, value);
+    }
+
+    /// Get the component with a given id, whether it is present or absent.
+    /// Returns `None` if the id is invalid.
+    ///
+    /// Queries access the optional components this way, as `Option<&T>` (e.g. `&damage`),
+    /// while `&damage.Some` accesses only the present ones with [`Storage::get`].
+    pub fn get_optional(&self, id: usize) -> Option<Option<&T>> {
+        (id < self.len).then(|| self.get(id))
+    }
+
+    /// Get the component with a given id mutably, whether it is present or absent.
+    /// Returns `None` if the id is invalid.
+    pub fn get_optional_mut(&mut self, id: usize) -> Option<Option<&mut T>> {
+        if id >= self.len {
+            return None;
+        }
+        Some(self.get_mut(id))
+    }
+
+    #[doc(hidden)]
+    pub fn query_get(&self, id: usize) -> Option<Option<&T>> {
+        self.get_optional(id)
+    }
+
+    #[doc(hidden)]
+    pub fn query_get_mut(&mut self, id: usize) -> Option<Option<&mut T>> {
+        self.get_optional_mut(id)
+    }
+
+    #[doc(hidden)]
+    #[cfg(feature = "query_mut")]
+    pub unsafe fn query_get_many_unchecked_mut<'a, I: Iterator<Item = usize>>(
+        &'a mut self,
+        ids: I,
+    ) -> GetManyOptionalUncheckedMut<'a, T, Self, I>
+    where
+        T: 'a,
+    {
+        // SAFETY: the requirements are the same
+        unsafe { self.get_many_optional_unchecked_mut(ids) }
+    }
+
+    /// Insert an absent component, returning its id.
+    pub fn insert_none(&mut self) -> usize {
+        let id = self.values.insert(MaybeUninit::uninit());
+        self.push(id, false);
+        id
+    }
+
+    /// Insert an absent component, returning its id,
+    /// or an error if the storage rejects it, see [`Storage::try_insert`].
+    pub fn try_insert_none(&mut self) -> Result<usize, InsertError<()>> {
+        let id = self
+            .values
+            .try_insert(MaybeUninit::uninit())
+            .map_err(|err| err.map(|_| ()))?;
+        self.push(id, false);
+        Ok(id)
+    }
+
+    /// Insert an absent component with a given id.
+    /// Returns `false` if the id could not be used, see [`Storage::insert_with_id`].
+    pub fn insert_none_with_id(&mut self, id: usize) -> bool {
+        if self
+            .values
+            .insert_with_id(id, MaybeUninit::uninit())
+            .is_err()
+        {
+            return false;
+        }
+        self.push(id, false);
+        true
+    }
+
+    /// Replace the component with a given id, whether it is present or absent, returning the old one.
+    /// Returns `None` if the id is invalid.
+    pub fn replace(&mut self, id: usize, value: Option<T>) -> Option<Option<T>> {
+        if id >= self.len {
+            return None;
+        }
+        let was_present = self.is_present(id);
+        let is_present = value.is_some();
+        let slot = self.values.get_mut(id)?;
+        let old = core::mem::replace(slot, value.map_or(MaybeUninit::uninit(), MaybeUninit::new));
+        self.set_present(id, is_present);
+        // SAFETY: the bit was set, so the value is initialized
+        Some(was_present.then(|| unsafe { old.assume_init() }))
+    }
+
+    /// Remove a component with a given id, whether it is present or absent.
+    /// Returns `None` if the id is invalid.
+    pub fn take(&mut self, id: usize) -> Option<Option<T>> {
+        let was_present = self.is_present(id);
+        let value = self.values.remove(id)?;
+
+        // NOTE: the last component has been moved into the place of the removed one
+        let last = self.len - 1;
+        let last_present = self.is_present(last);
+        self.set_present(id, last_present);
+        self.set_present(last, false);
+        self.len = last;
+        if self.len % WORD_BITS == 0 {
+            self.present.pop();
+        }
+
+        // SAFETY: the bit was set, so the value is initialized
+        Some(was_present.then(|| unsafe { value.assume_init() }))
+    }
+
+    /// Remove all components, whether they are present or absent,
+    /// returning an iterator over them together with their id's.
+    pub fn drain_optional(&mut self) -> BitsetDrain<F, T> {
+        self.len = 0;
+        BitsetDrain {
+            values: self.values.drain(),
+            present: core::mem::take(&mut self.present),
+        }
+    }
+}
+
+impl<F: DenseFamily, T> Default for BitsetStorage<F, T> {
+    fn default() -> Self {
+        Self {
+            values: Default::default(),
+            present: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<F: DenseFamily, T: Clone> Clone for BitsetStorage<F, T> {
+    fn clone(&self) -> Self {
+        let mut storage = Self::default();
+        for id in 0..self.len {
+            match self.get(id) {
+                Some(value) => storage.insert(value.clone()),
+                None => storage.insert_none(),
+            };
+        }
+        storage
+    }
+}
+
+impl<F: DenseFamily, T> Drop for BitsetStorage<F, T> {
+    fn drop(&mut self) {
+        for id in 0..self.len {
+            if self.is_present(id) {
+                if let Some(value) = self.values.get_mut(id) {
+                    // SAFETY: the bit is set, so the value is initialized
+                    unsafe { value.assume_init_drop() };
+                }
+            }
+        }
+    }
+}
+
+unsafe impl<F: DenseFamily, T> Storage<T> for BitsetStorage<F, T> {
+    type Family = BitsetFamily<F>;
+    type Id = usize;
+    type IdsIter<'a>
+        = BitsetIds<'a>
+    where
+        Self: 'a;
+    type Drain =
+        core::iter::FilterMap<BitsetDrain<F, T>, fn((usize, Option<T>)) -> Option<(usize, T)>>;
+    fn ids(&self) -> Self::IdsIter<'_> {
+        // SAFETY: only the present components, which are a subset of the valid id's
+        BitsetIds {
+            words: &self.present,
+            word_index: 0,
+            word: self.present.first().copied().unwrap_or(0),
+        }
+    }
+    fn insert(&mut self, value: T) -> Self::Id {
+        let id = self.values.insert(MaybeUninit::new(value));
+        self.push(id, true);
+        id
+    }
+    fn try_insert(&mut self, value: T) -> Result<Self::Id, InsertError<T>> {
+        let id = self
+            .values
+            .try_insert(MaybeUninit::new(value))
+            // SAFETY: the value has just been initialized
+            .map_err(|err| err.map(|value| unsafe { value.assume_init() }))?;
+        self.push(id, true);
+        Ok(id)
+    }
+    fn insert_with_id(&mut self, id: Self::Id, value: T) -> Result<(), T> {
+        self.values
+            .insert_with_id(id, MaybeUninit::new(value))
+            // SAFETY: the value has just been initialized
+            .map_err(|value| unsafe { value.assume_init() })?;
+        self.push(id, true);
+        Ok(())
+    }
+    fn get(&self, id: Self::Id) -> Option<&T> {
+        if !self.is_present(id) {
+            return None;
+        }
+        let value = self.values.get(id)?;
+        // SAFETY: the bit is set, so the value is initialized
+        Some(unsafe { value.assume_init_ref() })
+    }
+    fn get_mut(&mut self, id: Self::Id) -> Option<&mut T> {
+        if !self.is_present(id) {
+            return None;
+        }
+        let value = self.values.get_mut(id)?;
+        // SAFETY: the bit is set, so the value is initialized
+        Some(unsafe { value.assume_init_mut() })
+    }
+    fn remove(&mut self, id: Self::Id) -> Option<T> {
+        self.take(id).flatten()
+    }
+    fn drain(&mut self) -> Self::Drain {
+        self.drain_optional().filter_map(present as fn(_) -> _)
+    }
+    fn reorder(&mut self, order: &[Self::Id]) -> Vec<Self::Id> {
+        // NOTE: `order` contains the absent entries as well
+        let mut present = Vec::with_capacity(self.present.len());
+        for (new_id, &id) in order.iter().enumerate() {
+            if new_id % WORD_BITS == 0 {
+                present.push(0);
+            }
+            if self.is_present(id) {
+                present[new_id / WORD_BITS] |= 1 << (new_id % WORD_BITS);
+            }
+        }
+        // NOTE: if reordering the values panics, they are leaked instead of being dropped uninitialized
+        let len = core::mem::take(&mut self.len);
+        self.present.clear();
+        let new_ids = self.values.reorder(order);
+        self.present = present;
+        self.len = len;
+        new_ids
+    }
+}
+
+/// Iterator over the id's of the present components in a [`BitsetStorage`].
+#[derive(Clone)]
+pub struct BitsetIds<'a> {
+    words: &'a [u64],
+    word_index: usize,
+    /// The remaining bits of the current word.
+    word: u64,
+}
+
+impl Iterator for BitsetIds<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Skip the absent components a word at a time
+        while self.word == 0 {
+            self.word_index += 1;
+            self.word = *self.words.get(self.word_index)?;
+        }
+        let bit = self.word.trailing_zeros() as usize;
+        self.word &= self.word - 1;
+        Some(self.word_index * WORD_BITS + bit)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let rest = self
+            .words
+            .get(self.word_index + 1..)
+            .unwrap_or_default()
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum::<usize>();
+        let len = self.word.count_ones() as usize + rest;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for BitsetIds<'_> {}
+
+/// An owning iterator over the present and absent components of a [`BitsetStorage`],
+/// created by [`BitsetStorage::drain_optional`].
+pub struct BitsetDrain<F: DenseFamily, T> {
+    values: <F::Storage<MaybeUninit<T>> as Storage<MaybeUninit<T>>>::Drain,
+    present: Vec<u64>,
+}
+
+impl<F: DenseFamily, T> Iterator for BitsetDrain<F, T> {
+    type Item = (usize, Option<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (id, value) = self.values.next()?;
+        let is_present = self.present[id / WORD_BITS] & (1 << (id % WORD_BITS)) != 0;
+        // SAFETY: the bit was set, so the value is initialized
+        Some((id, is_present.then(|| unsafe { value.assume_init() })))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+impl<F: DenseFamily, T> Drop for BitsetDrain<F, T> {
+    fn drop(&mut self) {
+        // Drop the remaining present components
+        self.by_ref().for_each(drop);
+    }
+}
+
+/// Family of [`BitsetStorage<F, T>`] storages.
+pub struct BitsetFamily<F: DenseFamily>(core::marker::PhantomData<F>);
+
+impl<F: DenseFamily> StorageFamily for BitsetFamily<F> {
+    type Id = usize;
+    type Storage<T> = BitsetStorage<F, T>;
+}
+
+/// A storage of `bool` tags (e.g. `selected` or `frozen`), which keeps only a bitset inside.
+///
+/// Used for `#[split(tag)]` fields of type `bool`. The id's follow the [`DenseFamily`],
+/// so that the tags stay consistent with the other columns of the archetype.
+///
+/// Since the tags are bits, they cannot be borrowed mutably, so it is not a [`Storage`].
+/// Instead, they are changed with [`TagStorage::set`], or in bulk with [`TagStorage::set_many`] and [`TagStorage::set_all`].
+/// With [`TagStorage::get_mut`], in the `RefMut` structs, and in queries (e.g. `&mut selected`),
+/// they are accessed through a [`TagMut`], which writes the tag back when dropped.
+///
+/// # Example
+///
+/// ```
+/// # use stecs::prelude::*;
+/// #[derive(SplitFields)]
+/// struct Unit {
+///     health: i64,
+///     #[split(tag)]
+///     selected: bool,
+/// }
+///
+/// let mut units: StructOf<Vec<Unit>> = Default::default();
+/// let ids: Vec<_> = (0..5).map(|health| units.insert(Unit { health, selected: false })).collect();
+///
+/// units.selected.set_many(ids[1..4].iter().copied(), true);
+/// units.selected.set(ids[2], false);
+///
+/// let selected: Vec<i64> = query!(units, (&health, &selected.Get.True))
+///     .map(|(health, _)| *health)
+///     .collect();
+/// assert_eq!(selected, [1, 3]);
+/// assert_eq!(units.selected.tagged().collect::<Vec<_>>(), [ids[1], ids[3]]);
+///
+/// for (health, mut selected) in query!(units, (&health, &mut selected)) {
+///     *selected = *health % 2 == 0;
+/// }
+/// assert_eq!(units.selected.tagged().collect::<Vec<_>>(), [ids[0], ids[2], ids[4]]);
+///
+/// let mut unit = units.get_mut(ids[0]).unwrap();
+/// *unit.health += 1;
+/// *unit.selected = false;
+/// drop(unit);
+/// assert!(!units.selected.is_set(ids[0]));
+/// ```
+///
+pub struct TagStorage<F: DenseFamily> {
+    /// The tags, set to zero past `len`.
+    bits: Vec<u64>,
+    len: usize,
+    phantom: core::marker::PhantomData<F>,
+}
+
+impl<F: DenseFamily> TagStorage<F> {
+    /// Check whether the tag is set for the given id.
+    /// Returns `false` for invalid id's.
+    pub fn is_set(&self, id: usize) -> bool {
+        id < self.len && self.bits[id / WORD_BITS] & (1 << (id % WORD_BITS)) != 0
+    }
+
+    /// Set or clear the tag for the given id, returning the previous value,
+    /// or `None` if the id is invalid.
+    pub fn set(&mut self, id: usize, value: bool) -> Option<bool> {
+        if id >= self.len {
+            return None;
+        }
+        let old = self.is_set(id);
+        let mask = 1 << (id % WORD_BITS);
+        if value {
+            self.bits[id / WORD_BITS] |= mask;
+        } else {
+            self.bits[id / WORD_BITS] &= !mask;
+        }
+        Some(old)
+    }
+
+    /// Set or clear the tags for all given id's, skipping the invalid ones.
+    pub fn set_many(&mut self, ids: impl IntoIterator<Item = usize>, value: bool) {
+        for id in ids {
+            self.set(id, value);
+        }
+    }
+
+    /// Set or clear the tags for all entities.
+    pub fn set_all(&mut self, value: bool) {
+        let fill = if value { u64::MAX } else { 0 };
+        self.bits.fill(fill);
+        // Keep the bits past `len` cleared
+        if let Some(last) = self.bits.last_mut() {
+            let rest = self.len % WORD_BITS;
+            if rest != 0 {
+                *last &= (1 << rest) - 1;
+            }
+        }
+    }
+
+    /// Iterate over the id's that have the tag set, skipping the rest a whole word of the bitset at a time.
+    pub fn tagged(&self) -> BitsetIds<'_> {
+        BitsetIds {
+            words: &self.bits,
+            word_index: 0,
+            word: self.bits.first().copied().unwrap_or(0),
+        }
+    }
+
+    /// Returns the id's of all entities, whether their tag is set or not.
+    pub fn ids(&self) -> core::ops::Range<usize> {
+        0..self.len
+    }
+
+    /// Insert a tag for a new entity, returning its id.
+    pub fn insert(&mut self, value: bool) -> usize {
+        let id = self.len;
+        if id % WORD_BITS == 0 {
+            self.bits.push(0);
+        }
+        self.len += 1;
+        self.set(id, value);
+        id
+    }
+
+    /// Insert a tag with a given id, returning the value back if the id cannot be used.
+    ///
+    /// The id's are indices, so only the next one is available, like in the [`DenseFamily`].
+    pub fn insert_with_id(&mut self, id: usize, value: bool) -> Result<(), bool> {
+        if id != self.len {
+            return Err(value);
+        }
+        self.insert(value);
+        Ok(())
+    }
+
+    /// Get the tag for the given id, or `None` if the id is invalid.
+    pub fn get(&self, id: usize) -> Option<&bool> {
+        (id < self.len).then_some(if self.is_set(id) { &true } else { &false })
+    }
+
+    /// Get a mutable proxy of the tag for the given id, or `None` if the id is invalid.
+    pub fn get_mut(&mut self, id: usize) -> Option<TagMut<'_>> {
+        if id >= self.len {
+            return None;
+        }
+        // SAFETY: the id is valid, and the storage is borrowed mutably
+        Some(unsafe { TagMut::new(self.bits.as_mut_ptr(), id) })
+    }
+
+    /// Remove the tag with a given id, returning its value, or `None` if the id is invalid.
+    ///
+    /// The last tag is moved into the place of the removed one, like in the [`DenseFamily`].
+    pub fn remove(&mut self, id: usize) -> Option<bool> {
+        if id >= self.len {
+            return None;
+        }
+        let value = self.is_set(id);
+
+        let last = self.len - 1;
+        let last_value = self.is_set(last);
+        self.set(id, last_value);
+        self.set(last, false);
+        self.len = last;
+        if self.len % WORD_BITS == 0 {
+            self.bits.pop();
+        }
+        Some(value)
+    }
+
+    /// Remove all tags, returning an iterator over them together with their id's.
+    pub fn drain(&mut self) -> TagDrain {
+        let len = core::mem::take(&mut self.len);
+        TagDrain {
+            bits: core::mem::take(&mut self.bits),
+            ids: 0..len,
+        }
+    }
+
+    /// Rebuild the storage in the given order, and return the new id's, see [`Storage::reorder`].
+    ///
+    /// # Panics
+    /// Panics if `order` does not contain every id.
+    ///
+    pub fn reorder(&mut self, order: &[usize]) -> Vec<usize> {
+        assert_eq!(order.len(), self.len, "`order` must contain every id");
+        let mut bits = Vec::with_capacity(self.bits.len());
+        for (new_id, &id) in order.iter().enumerate() {
+            if new_id % WORD_BITS == 0 {
+                bits.push(0);
+            }
+            if self.is_set(id) {
+                bits[new_id / WORD_BITS] |= 1 << (new_id % WORD_BITS);
+            }
+        }
+        self.bits = bits;
+        (0..self.len).collect()
+    }
+
+    #[doc(hidden)]
+    pub fn query_get(&self, id: usize) -> Option<&bool> {
+        self.get(id)
+    }
+
+    #[doc(hidden)]
+    pub fn query_get_mut(&mut self, id: usize) -> Option<TagMut<'_>> {
+        self.get_mut(id)
+    }
+
+    #[doc(hidden)]
+    #[cfg(feature = "query_mut")]
+    pub unsafe fn query_get_many_unchecked_mut<I: Iterator<Item = usize>>(
+        &mut self,
+        ids: I,
+    ) -> TagsMut<'_, I> {
+        // SAFETY: the requirements are the same
+        unsafe { self.get_many_unchecked_mut(ids) }
+    }
+
+    /// Get mutable proxies of the tags for the given id's.
+    ///
+    /// # Safety
+    /// The given `ids` must not repeat and must be valid id's in the storage.
+    ///
+    #[cfg(feature = "query_mut")]
+    pub unsafe fn get_many_unchecked_mut<I: Iterator<Item = usize>>(
+        &mut self,
+        ids: I,
+    ) -> TagsMut<'_, I> {
+        TagsMut {
+            bits: self.bits.as_mut_ptr(),
+            ids,
+            phantom: core::marker::PhantomData,
+        }
+    }
+
+    /// Get mutable proxies of the tags for the given id's, or `None` for invalid id's.
+    ///
+    /// # Safety
+    /// The given `ids` must not repeat.
+    ///
+    #[cfg(feature = "query_mut")]
+    pub unsafe fn get_many_optional_unchecked_mut<I: Iterator<Item = usize>>(
+        &mut self,
+        ids: I,
+    ) -> impl Iterator<Item = Option<TagMut<'_>>> {
+        let len = self.len;
+        let bits = self.bits.as_mut_ptr();
+        ids.map(move |id| {
+            // SAFETY: the id's are unique, and the storage is borrowed mutably
+            (id < len).then(|| unsafe { TagMut::new(bits, id) })
+        })
+    }
+}
+
+/// A mutable reference to a tag in a [`TagStorage`], which writes the tag back when dropped.
+pub struct TagMut<'a> {
+    word: *mut u64,
+    mask: u64,
+    value: bool,
+    phantom: core::marker::PhantomData<&'a mut u64>,
+}
+
+impl TagMut<'_> {
+    /// # Safety
+    /// The `id` must be valid, and the bits must be borrowed mutably for the lifetime of the proxy.
+    unsafe fn new(bits: *mut u64, id: usize) -> Self {
+        // NOTE: proxies of the tags in the same word only access it through the raw pointer
+        let word = unsafe { bits.add(id / WORD_BITS) };
+        let mask = 1 << (id % WORD_BITS);
+        Self {
+            word,
+            mask,
+            value: unsafe { *word } & mask != 0,
+            phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl core::ops::Deref for TagMut<'_> {
+    type Target = bool;
+
+    fn deref(&self) -> &bool {
+        &self.value
+    }
+}
+
+impl core::ops::DerefMut for TagMut<'_> {
+    fn deref_mut(&mut self) -> &mut bool {
+        &mut self.value
+    }
+}
+
+impl Drop for TagMut<'_> {
+    fn drop(&mut self) {
+        // SAFETY: the word is borrowed mutably for the lifetime of the proxy
+        unsafe {
+            if self.value {
+                *self.word |= self.mask;
+            } else {
+                *self.word &= !self.mask;
+            }
+        }
+    }
+}
+
+impl core::fmt::Debug for TagMut<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl PartialEq for TagMut<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for TagMut<'_> {}
+
+impl core::hash::Hash for TagMut<'_> {
+    fn hash<H: core::hash::Hasher>(&self, state: &mut H) {
+        self.value.hash(state);
+    }
+}
+
+/// Iterator over the mutable proxies of the tags with the given id's,
+/// returned by [`TagStorage::get_many_unchecked_mut`].
+pub struct TagsMut<'a, I> {
+    bits: *mut u64,
+    ids: I,
+    phantom: core::marker::PhantomData<&'a mut u64>,
+}
+
+impl<'a, I: Iterator<Item = usize>> Iterator for TagsMut<'a, I> {
+    type Item = TagMut<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next()?;
+        // SAFETY: the id's are unique and valid
+        Some(unsafe { TagMut::new(self.bits, id) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<I: DoubleEndedIterator<Item = usize>> DoubleEndedIterator for TagsMut<'_, I> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next_back()?;
+        // SAFETY: the id's are unique and valid
+        Some(unsafe { TagMut::new(self.bits, id) })
+    }
+}
+
+impl<I: ExactSizeIterator<Item = usize>> ExactSizeIterator for TagsMut<'_, I> {}
+
+impl<F: DenseFamily> Default for TagStorage<F> {
+    fn default() -> Self {
+        Self {
+            bits: Vec::new(),
+            len: 0,
+            phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<F: DenseFamily> Clone for TagStorage<F> {
+    fn clone(&self) -> Self {
+        Self {
+            bits: self.bits.clone(),
+            len: self.len,
+            phantom: core::marker::PhantomData,
+        }
+    }
+}
+
+/// An owning iterator over the tags of a [`TagStorage`], created by [`TagStorage::drain`].
+pub struct TagDrain {
+    bits: Vec<u64>,
+    ids: core::ops::Range<usize>,
+}
+
+impl Iterator for TagDrain {
+    type Item = (usize, bool);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next()?;
+        Some((id, self.bits[id / WORD_BITS] & (1 << (id % WORD_BITS)) != 0))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl ExactSizeIterator for TagDrain {}
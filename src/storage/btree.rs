@@ -0,0 +1,114 @@
+use crate::{
+    archetype::{SplitFields, StructOfAble},
+    storage::{
+        map::{IdMap, Map},
+        InsertError, StableFamily, Storage, StorageFamily,
+    },
+};
+
+use alloc::collections::{btree_map, BTreeMap};
+
+/// Identifier type for a [`BTreeStorage`].
+pub use super::map::Id;
+
+/// A storage that keeps values inside a [`BTreeMap`], so that the id's are iterated in sorted order.
+///
+/// Unlike [`HashStorage`](super::hashstorage::HashStorage), the iteration order is deterministic,
+/// and does not depend on the hash seed, which is useful for replays and lockstep networking.
+/// New entities get increasing id's, so they are iterated in the insertion order.
+///
+/// # Example
+///
+/// ```
+/// # use stecs::prelude::*;
+/// # use stecs::storage::btree::BTreeStorage;
+/// #[derive(SplitFields)]
+/// struct Unit {
+///     health: i64,
+/// }
+///
+/// let mut units: StructOf<BTreeStorage<Unit>> = Default::default();
+/// let ids: Vec<_> = (0..5).map(|health| units.insert(Unit { health })).collect();
+/// units.remove(ids[1]);
+///
+/// let healths: Vec<i64> = query!(units, (&health)).copied().collect();
+/// assert_eq!(healths, [0, 2, 3, 4]);
+///
+/// // Sorting reassigns the id's in the sorted order
+/// units.sort_by_key(|unit| -*unit.health);
+/// let healths: Vec<i64> = query!(units, (&health)).copied().collect();
+/// assert_eq!(healths, [4, 3, 2, 0]);
+/// ```
+///
+#[derive(Clone)]
+pub struct BTreeStorage<T> {
+    inner: IdMap<BTreeMap<Id, T>>,
+}
+
+impl<T> Default for BTreeStorage<T> {
+    fn default() -> Self {
+        Self {
+            inner: IdMap::default(),
+        }
+    }
+}
+
+impl<T> Map<T> for BTreeMap<Id, T> {
+    fn contains_key(&self, id: &Id) -> bool {
+        BTreeMap::contains_key(self, id)
+    }
+    fn insert(&mut self, id: Id, value: T) {
+        BTreeMap::insert(self, id, value);
+    }
+}
+
+unsafe impl<T> Storage<T> for BTreeStorage<T> {
+    type Family = BTreeFamily;
+    type Id = Id;
+    type IdsIter<'a>
+        = core::iter::Copied<btree_map::Keys<'a, Id, T>>
+    where
+        T: 'a;
+    type Drain = btree_map::IntoIter<Id, T>;
+    fn ids(&self) -> Self::IdsIter<'_> {
+        // SAFETY: `keys()` guarantees validity and uniqueness
+        self.inner.keys().copied()
+    }
+    fn insert(&mut self, value: T) -> Self::Id {
+        self.try_insert(value).unwrap_or_else(|err| panic!("{err}"))
+    }
+    fn try_insert(&mut self, value: T) -> Result<Self::Id, InsertError<T>> {
+        self.inner.try_insert(value)
+    }
+    fn insert_with_id(&mut self, id: Self::Id, value: T) -> Result<(), T> {
+        self.inner.insert_with_id(id, value)
+    }
+    fn get(&self, id: Self::Id) -> Option<&T> {
+        self.inner.get(&id)
+    }
+    fn get_mut(&mut self, id: Self::Id) -> Option<&mut T> {
+        self.inner.get_mut(&id)
+    }
+    fn remove(&mut self, id: Self::Id) -> Option<T> {
+        self.inner.remove(&id)
+    }
+    fn drain(&mut self) -> Self::Drain {
+        // NOTE: iterates in the order of the id's, which is the same for all columns
+        core::mem::take(&mut *self.inner).into_iter()
+    }
+}
+
+/// Family of [`BTreeStorage<T>`] storages.
+pub struct BTreeFamily;
+
+impl StorageFamily for BTreeFamily {
+    type Id = Id;
+    type Storage<T> = BTreeStorage<T>;
+}
+
+impl StableFamily for BTreeFamily {}
+
+impl<T: SplitFields<BTreeFamily>> StructOfAble for BTreeStorage<T> {
+    type Struct = T;
+    type Family = BTreeFamily;
+}
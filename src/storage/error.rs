@@ -0,0 +1,42 @@
+use core::fmt;
+
+/// The reason why a value could not be inserted, returned by [`Storage::try_insert`](super::Storage::try_insert).
+///
+/// Holds the rejected value, so that it can be recovered with [`InsertError::into_inner`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertError<T> {
+    /// The storage is full (e.g. a fixed-capacity storage).
+    CapacityExceeded(T),
+    /// The storage has run out of unique id's.
+    IdExhausted(T),
+}
+
+impl<T> InsertError<T> {
+    /// Get the rejected value back.
+    pub fn into_inner(self) -> T {
+        match self {
+            Self::CapacityExceeded(value) | Self::IdExhausted(value) => value,
+        }
+    }
+
+    /// Map the rejected value, keeping the reason.
+    ///
+    /// Used to rebuild the whole entity when one of its components is rejected.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> InsertError<U> {
+        match self {
+            Self::CapacityExceeded(value) => InsertError::CapacityExceeded(f(value)),
+            Self::IdExhausted(value) => InsertError::IdExhausted(f(value)),
+        }
+    }
+}
+
+impl<T> fmt::Display for InsertError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CapacityExceeded(_) => write!(f, "storage capacity exceeded"),
+            Self::IdExhausted(_) => write!(f, "storage has run out of unique id's"),
+        }
+    }
+}
+
+impl<T: fmt::Debug> core::error::Error for InsertError<T> {}
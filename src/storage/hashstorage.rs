@@ -1,46 +1,112 @@
 use crate::{
     archetype::{SplitFields, StructOfAble},
-    storage::{Storage, StorageFamily},
+    storage::{
+        map::{IdMap, Map},
+        InsertError, StableFamily, Storage, StorageFamily,
+    },
 };
 
-use std::collections::HashMap;
+use alloc::vec::Vec;
+use core::hash::BuildHasher;
+use hashbrown::HashMap;
+
+/// The default randomly seeded hasher builder of [`HashStorage`].
+pub use hashbrown::DefaultHashBuilder;
+
+/// A hasher builder with a fixed seed, so that the iteration order of a [`HashStorage`]
+/// is the same between runs (given the same sequence of operations).
+///
+/// The order may still change between platforms (e.g. with a different pointer width)
+/// or versions of the hashing dependencies, so it should not be persisted or relied upon.
+/// Use [`BTreeFamily`](super::btree::BTreeFamily) for an order that is fully specified.
+pub type DeterministicState = foldhash::fast::FixedState;
+
+/// A [`HashStorage`] with a deterministic iteration order, see [`DeterministicState`].
+pub type DeterministicHashStorage<T> = HashStorage<T, DeterministicState>;
+
+/// Family of [`DeterministicHashStorage<T>`] storages.
+pub type DeterministicHashFamily = HashFamily<DeterministicState>;
 
 /// Identifier type for a [`HashStorage`].
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Id(u64);
+pub use super::map::Id;
 
-/// A storage that keeps values inside a [`HashMap`].
+/// A storage that keeps values inside a [`HashMap`], hashing the id's with `S`.
+///
+/// By default, the hasher is randomly seeded, so the iteration order differs between runs.
+/// Use [`DeterministicHashStorage`] (or [`DeterministicHashFamily`]) to make it reproducible within a build,
+/// or [`BTreeFamily`](super::btree::BTreeFamily) to iterate in the order of the id's.
+///
+/// The columns of an archetype iterate in different orders, so they are drained by taking
+/// the id's of one column out of the others, see [`Storage::drain_take`].
+///
+/// # Example
+///
+/// ```
+/// # use stecs::prelude::*;
+/// # use stecs::storage::hashstorage::DeterministicHashFamily;
+/// #[derive(SplitFields)]
+/// struct Unit {
+///     health: i64,
+/// }
+///
+/// let spawn = || {
+///     let mut units: UnitStructOf<DeterministicHashFamily> = Default::default();
+///     for health in 0..8 {
+///         units.insert(Unit { health });
+///     }
+///     units
+/// };
+/// // The order is arbitrary, but the same for the same sequence of operations
+/// let (a, b) = (spawn(), spawn());
+/// let healths = |units: &UnitStructOf<DeterministicHashFamily>| {
+///     query!(units, (&health)).copied().collect::<Vec<i64>>()
+/// };
+/// assert_eq!(healths(&a), healths(&b));
+/// ```
+///
 #[derive(Clone)]
-pub struct HashStorage<T> {
-    next_id: Id,
-    inner: HashMap<Id, T>,
+pub struct HashStorage<T, S = DefaultHashBuilder> {
+    inner: IdMap<HashMap<Id, T, S>>,
 }
 
-impl<T> Default for HashStorage<T> {
+impl<T, S: Default> Default for HashStorage<T, S> {
     fn default() -> Self {
         Self {
-            next_id: Id(0),
-            inner: Default::default(),
+            inner: IdMap::default(),
         }
     }
 }
 
-unsafe impl<T> Storage<T> for HashStorage<T> {
-    type Family = HashFamily;
+impl<T, S: BuildHasher> Map<T> for HashMap<Id, T, S> {
+    fn contains_key(&self, id: &Id) -> bool {
+        HashMap::contains_key(self, id)
+    }
+    fn insert(&mut self, id: Id, value: T) {
+        HashMap::insert(self, id, value);
+    }
+}
+
+unsafe impl<T, S: BuildHasher + Default> Storage<T> for HashStorage<T, S> {
+    type Family = HashFamily<S>;
     type Id = Id;
-    fn ids(&self) -> impl Iterator<Item = Self::Id> + Clone {
+    type IdsIter<'a>
+        = core::iter::Copied<hashbrown::hash_map::Keys<'a, Id, T>>
+    where
+        T: 'a,
+        S: 'a;
+    type Drain = HashDrain<T, S>;
+    fn ids(&self) -> Self::IdsIter<'_> {
         // SAFETY: `keys()` guarantees validity and uniqueness
         self.inner.keys().copied()
     }
     fn insert(&mut self, value: T) -> Self::Id {
-        let id = self.next_id;
-        self.next_id.0 += 1;
-        let res = self.inner.insert(id, value);
-        assert!(
-            res.is_none(),
-            "Failed to generate a unique id in a collection"
-        );
-        id
+        self.try_insert(value).unwrap_or_else(|err| panic!("{err}"))
+    }
+    fn try_insert(&mut self, value: T) -> Result<Self::Id, InsertError<T>> {
+        self.inner.try_insert(value)
+    }
+    fn insert_with_id(&mut self, id: Self::Id, value: T) -> Result<(), T> {
+        self.inner.insert_with_id(id, value)
     }
     fn get(&self, id: Self::Id) -> Option<&T> {
         self.inner.get(&id)
@@ -51,30 +117,65 @@ unsafe impl<T> Storage<T> for HashStorage<T> {
     fn remove(&mut self, id: Self::Id) -> Option<T> {
         self.inner.remove(&id)
     }
-    #[cfg(feature = "query_mut")]
-    unsafe fn get_many_unchecked_mut<'a>(
-        &'a mut self,
-        ids: impl Iterator<Item = Self::Id>,
-    ) -> impl Iterator<Item = &'a mut T>
-    where
-        T: 'a,
-    {
-        ids.map(move |i| {
-            let r = self.get_mut(i).expect("invalid id: entry absent");
-            unsafe { &mut *(r as *mut T) }
-        })
+    fn drain(&mut self) -> Self::Drain {
+        HashDrain {
+            inner: core::mem::take(&mut *self.inner),
+            iter: None,
+        }
+    }
+    fn drain_take(drain: &mut Self::Drain, id: Self::Id) -> Option<T> {
+        // NOTE: the order of a `HashMap` differs between columns, so take by the id
+        drain.inner.remove(&id)
+    }
+    fn reorder(&mut self, order: &[Self::Id]) -> Vec<Self::Id> {
+        // NOTE: the order is arbitrary anyway, so the id's are kept the same,
+        // but the map is still rebuilt to shrink it
+        let mut old = core::mem::take(&mut *self.inner);
+        *self.inner = order
+            .iter()
+            .map(|&id| (id, old.remove(&id).expect("invalid id: entry absent")))
+            .collect();
+        order.to_vec()
     }
 }
 
-/// Family of [`HashStorage<T>`] storages.
-pub struct HashFamily;
+/// An owning iterator over the components of a [`HashStorage`], created by [`Storage::drain`].
+///
+/// The components can also be taken by their id's with [`Storage::drain_take`], until the iteration starts.
+pub struct HashDrain<T, S> {
+    inner: HashMap<Id, T, S>,
+    iter: Option<hashbrown::hash_map::IntoIter<Id, T>>,
+}
+
+impl<T, S: Default> Iterator for HashDrain<T, S> {
+    type Item = (Id, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let inner = &mut self.inner;
+        self.iter
+            .get_or_insert_with(|| core::mem::take(inner).into_iter())
+            .next()
+    }
 
-impl StorageFamily for HashFamily {
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.iter {
+            Some(iter) => iter.size_hint(),
+            None => (self.inner.len(), Some(self.inner.len())),
+        }
+    }
+}
+
+/// Family of [`HashStorage<T, S>`] storages, using the hasher builder `S`.
+pub struct HashFamily<S = DefaultHashBuilder>(core::marker::PhantomData<S>);
+
+impl<S: BuildHasher + Default> StorageFamily for HashFamily<S> {
     type Id = Id;
-    type Storage<T> = HashStorage<T>;
+    type Storage<T> = HashStorage<T, S>;
 }
 
-impl<T: SplitFields<HashFamily>> StructOfAble for HashStorage<T> {
+impl<S: BuildHasher + Default> StableFamily for HashFamily<S> {}
+
+impl<T: SplitFields<HashFamily<S>>, S: BuildHasher + Default> StructOfAble for HashStorage<T, S> {
     type Struct = T;
-    type Family = HashFamily;
+    type Family = HashFamily<S>;
 }
@@ -1,4 +1,5 @@
-use std::{collections::HashMap, hash::Hash};
+use core::hash::Hash;
+use hashbrown::HashMap;
 
 use super::{Storage, StorageFamily};
 
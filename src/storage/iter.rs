@@ -0,0 +1,117 @@
+use super::Storage;
+
+use core::marker::PhantomData;
+
+/// Iterator over mutable references to the components with the given id's,
+/// returned by [`Storage::get_many_unchecked_mut`].
+pub struct GetManyUncheckedMut<'a, T, S, I> {
+    storage: *mut S,
+    ids: I,
+    phantom: PhantomData<&'a mut T>,
+}
+
+impl<'a, T, S: Storage<T> + 'a, I: Iterator<Item = S::Id>> GetManyUncheckedMut<'a, T, S, I> {
+    /// # Safety
+    /// The given `ids` must not repeat and must be valid and present id's in the storage.
+    #[cfg(feature = "query_mut")]
+    pub(crate) unsafe fn new(storage: &'a mut S, ids: I) -> Self {
+        Self {
+            storage,
+            ids,
+            phantom: PhantomData,
+        }
+    }
+
+    fn get(&mut self, id: S::Id) -> &'a mut T {
+        // SAFETY: the id's are unique, so the references do not alias
+        let storage = unsafe { &mut *self.storage };
+        storage.get_mut(id).expect("invalid id: entry absent")
+    }
+}
+
+impl<'a, T: 'a, S: Storage<T> + 'a, I: Iterator<Item = S::Id>> Iterator
+    for GetManyUncheckedMut<'a, T, S, I>
+{
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next()?;
+        Some(self.get(id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<'a, T: 'a, S: Storage<T> + 'a, I: DoubleEndedIterator<Item = S::Id>> DoubleEndedIterator
+    for GetManyUncheckedMut<'a, T, S, I>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next_back()?;
+        Some(self.get(id))
+    }
+}
+
+impl<'a, T: 'a, S: Storage<T> + 'a, I: ExactSizeIterator<Item = S::Id>> ExactSizeIterator
+    for GetManyUncheckedMut<'a, T, S, I>
+{
+}
+
+/// Iterator over optional mutable references to the components with the given id's,
+/// returned by [`Storage::get_many_optional_unchecked_mut`].
+pub struct GetManyOptionalUncheckedMut<'a, T, S, I> {
+    storage: *mut S,
+    ids: I,
+    phantom: PhantomData<&'a mut T>,
+}
+
+impl<'a, T, S: Storage<T> + 'a, I: Iterator<Item = S::Id>>
+    GetManyOptionalUncheckedMut<'a, T, S, I>
+{
+    /// # Safety
+    /// The given `ids` must not repeat and must be valid id's in the storage.
+    #[cfg(feature = "query_mut")]
+    pub(crate) unsafe fn new(storage: &'a mut S, ids: I) -> Self {
+        Self {
+            storage,
+            ids,
+            phantom: PhantomData,
+        }
+    }
+
+    fn get(&mut self, id: S::Id) -> Option<&'a mut T> {
+        // SAFETY: the id's are unique, so the references do not alias
+        let storage = unsafe { &mut *self.storage };
+        storage.get_mut(id)
+    }
+}
+
+impl<'a, T: 'a, S: Storage<T> + 'a, I: Iterator<Item = S::Id>> Iterator
+    for GetManyOptionalUncheckedMut<'a, T, S, I>
+{
+    type Item = Option<&'a mut T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next()?;
+        Some(self.get(id))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<'a, T: 'a, S: Storage<T> + 'a, I: DoubleEndedIterator<Item = S::Id>> DoubleEndedIterator
+    for GetManyOptionalUncheckedMut<'a, T, S, I>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next_back()?;
+        Some(self.get(id))
+    }
+}
+
+impl<'a, T: 'a, S: Storage<T> + 'a, I: ExactSizeIterator<Item = S::Id>> ExactSizeIterator
+    for GetManyOptionalUncheckedMut<'a, T, S, I>
+{
+}
@@ -0,0 +1,100 @@
+use crate::storage::InsertError;
+
+/// Identifier type for the map-based storages,
+/// i.e. [`BTreeStorage`](super::btree::BTreeStorage) and `HashStorage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Id(u64);
+
+impl Id {
+    /// Make an id from its raw value, e.g. to restore a snapshot with [`Storage::insert_with_id`](super::Storage::insert_with_id).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use stecs::prelude::*;
+    /// # use stecs::storage::btree::{BTreeStorage, Id};
+    /// let mut storage: BTreeStorage<&str> = Default::default();
+    /// let id = Id::from_raw(7);
+    /// assert!(storage.insert_with_id(id, "seven").is_ok());
+    /// assert_eq!(storage.get(Id::from_raw(id.to_raw())), Some(&"seven"));
+    /// ```
+    ///
+    pub const fn from_raw(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    /// Get the raw value of the id.
+    pub const fn to_raw(self) -> u64 {
+        self.0
+    }
+}
+
+/// A map from the id's to the components, see [`IdMap`].
+pub(super) trait Map<T> {
+    fn contains_key(&self, id: &Id) -> bool;
+    fn insert(&mut self, id: Id, value: T);
+}
+
+/// A map together with the counter of the next id.
+///
+/// Dereferences to the map, so taking it out (e.g. when draining) keeps the counter.
+#[derive(Clone)]
+pub(super) struct IdMap<M> {
+    /// Always above the used id's, so that the new id's are unique.
+    next_id: Id,
+    map: M,
+}
+
+impl<M: Default> Default for IdMap<M> {
+    fn default() -> Self {
+        Self {
+            next_id: Id(0),
+            map: M::default(),
+        }
+    }
+}
+
+impl<M> IdMap<M> {
+    /// Insert a component with a new id, see [`Storage::try_insert`](super::Storage::try_insert).
+    pub fn try_insert<T>(&mut self, value: T) -> Result<Id, InsertError<T>>
+    where
+        M: Map<T>,
+    {
+        let Some(next_id) = self.next_id.0.checked_add(1) else {
+            return Err(InsertError::IdExhausted(value));
+        };
+        let id = self.next_id;
+        self.next_id.0 = next_id;
+        self.map.insert(id, value);
+        Ok(id)
+    }
+
+    /// Insert a component with a given id, see [`Storage::insert_with_id`](super::Storage::insert_with_id).
+    pub fn insert_with_id<T>(&mut self, id: Id, value: T) -> Result<(), T>
+    where
+        M: Map<T>,
+    {
+        if self.map.contains_key(&id) {
+            return Err(value);
+        }
+        // Make sure generated id's do not collide with the chosen one
+        self.next_id.0 = self.next_id.0.max(id.0.saturating_add(1));
+        self.map.insert(id, value);
+        Ok(())
+    }
+}
+
+impl<M> core::ops::Deref for IdMap<M> {
+    type Target = M;
+
+    fn deref(&self) -> &M {
+        &self.map
+    }
+}
+
+impl<M> core::ops::DerefMut for IdMap<M> {
+    fn deref_mut(&mut self) -> &mut M {
+        &mut self.map
+    }
+}
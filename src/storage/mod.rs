@@ -1,12 +1,30 @@
 /// Arena storage.
 #[cfg(feature = "arena")]
 pub mod arena;
+/// Fixed-capacity inline storage.
+#[cfg(feature = "arrayvec")]
+pub mod arrayvec;
+/// Optional components and tags stored as bitsets.
+pub mod bitset;
+/// Ordered storage.
+pub mod btree;
 /// Hash storage.
 #[cfg(feature = "hashstorage")]
 pub mod hashstorage;
+/// Optional storage adapter.
+pub mod option;
 /// Vec storage.
 pub mod vec;
 
+mod error;
+mod iter;
+mod map;
+
+pub use self::error::*;
+pub use self::iter::*;
+
+use alloc::vec::Vec;
+
 /// A storage of components.
 ///
 /// # Safety
@@ -20,21 +38,83 @@ pub unsafe trait Storage<T>: Default {
     /// Type of the identifier used for components/entities.
     type Id: Copy;
 
-    fn phantom_data(&self) -> std::marker::PhantomData<Self::Family> {
+    /// Type of the iterator over the id's of the storage.
+    ///
+    /// **Note**: [`Clone`](trait@core::clone::Clone) is constrained for sharing between multiple fields' accessors when implementing [`get_many_unchecked_mut`](Storage::get_many_unchecked_mut).
+    /// If possible, it should also implement [`ExactSizeIterator`] and [`DoubleEndedIterator`], which are then propagated to the queries.
+    type IdsIter<'a>: Iterator<Item = Self::Id> + Clone
+    where
+        Self: 'a;
+    /// Type of the owning iterator returned by [`Storage::drain`].
+    type Drain: Iterator<Item = (Self::Id, T)>;
+
+    fn phantom_data(&self) -> core::marker::PhantomData<Self::Family> {
         Default::default()
     }
     /// Returns the unique id's of all active entities in the storage in an arbitrary order.
-    ///
-    /// **Note**: [`Clone`](trait@std::clone::Clone) is constrained for sharing between multiple fields' accessors when implementing [`get_many_unchecked_mut`](Storage::get_many_unchecked_mut).
-    fn ids(&self) -> impl Iterator<Item = Self::Id> + Clone;
+    fn ids(&self) -> Self::IdsIter<'_>;
     /// Insert a new component, returning its id.
+    ///
+    /// # Panics
+    /// May panic if the storage rejects the value, see [`Storage::try_insert`].
+    ///
     fn insert(&mut self, value: T) -> Self::Id;
+    /// Insert a new component, returning its id,
+    /// or returning the value back if the storage rejects it (e.g. a full fixed-capacity storage).
+    fn try_insert(&mut self, value: T) -> Result<Self::Id, InsertError<T>> {
+        Ok(self.insert(value))
+    }
+    /// Insert a new component with a given id, e.g. to restore a snapshot.
+    ///
+    /// Fails, returning the value back, if the id is already occupied,
+    /// or if the storage cannot use that id (by default, choosing the id is not supported).
+    ///
+    /// `Vec`-like storages only accept the next index, while `HashStorage` and `BTreeStorage` accept any free id.
+    /// `SlotMap` (and so `Arena`) cannot insert with a chosen key, so it always fails.
+    fn insert_with_id(&mut self, id: Self::Id, value: T) -> Result<(), T> {
+        let _ = id;
+        Err(value)
+    }
     /// Get an immutable reference to a component a given id.
     fn get(&self, id: Self::Id) -> Option<&T>;
     /// Get a mutable reference to a component a given id.
     fn get_mut(&mut self, id: Self::Id) -> Option<&mut T>;
     /// Remove an component with a given id.
     fn remove(&mut self, id: Self::Id) -> Option<T>;
+    /// Remove all components, returning an iterator over them together with their id's.
+    ///
+    /// Storages of the same family with the same id's must yield them in the same order,
+    /// so that the columns of an archetype can be drained together,
+    /// unless they override [`Storage::drain_take`].
+    fn drain(&mut self) -> Self::Drain;
+    /// Take the component with a given id out of a drain, see [`Storage::drain`].
+    ///
+    /// An archetype is drained by following the id's of one of its columns, and taking the same id's from the other ones.
+    /// By default, the drains yield the id's in the same order, so the next component is taken.
+    fn drain_take(drain: &mut Self::Drain, id: Self::Id) -> Option<T> {
+        let _ = id;
+        drain.next().map(|(_, value)| value)
+    }
+
+    /// Rebuild the storage, inserting the components in the given order, and return their new id's.
+    ///
+    /// `order` must contain each id of the storage exactly once.
+    /// Storages of the same family must assign the same new id's for the same order,
+    /// so that all columns of an archetype stay consistent.
+    ///
+    /// # Panics
+    /// May panic if `order` contains invalid or duplicate id's.
+    ///
+    fn reorder(&mut self, order: &[Self::Id]) -> Vec<Self::Id> {
+        let mut old = core::mem::take(self);
+        order
+            .iter()
+            .map(|&id| {
+                let value = old.remove(id).expect("invalid id: entry absent");
+                self.insert(value)
+            })
+            .collect()
+    }
 
     /// Get mutable references to all id's in the iterator.
     ///
@@ -42,14 +122,90 @@ pub unsafe trait Storage<T>: Default {
     /// The given `ids` must not repeat and must be valid and present id's in the storage.
     ///
     #[cfg(feature = "query_mut")]
-    unsafe fn get_many_unchecked_mut<'a>(
+    unsafe fn get_many_unchecked_mut<'a, I: Iterator<Item = Self::Id>>(
         &'a mut self,
-        ids: impl Iterator<Item = Self::Id>,
-    ) -> impl Iterator<Item = &'a mut T>
+        ids: I,
+    ) -> GetManyUncheckedMut<'a, T, Self, I>
     where
-        T: 'a;
+        T: 'a,
+    {
+        GetManyUncheckedMut::new(self, ids)
+    }
+
+    /// Get mutable references to all id's in the iterator, or `None` for absent components.
+    ///
+    /// # Safety
+    /// The given `ids` must not repeat and must be valid id's in the storage.
+    ///
+    #[cfg(feature = "query_mut")]
+    unsafe fn get_many_optional_unchecked_mut<'a, I: Iterator<Item = Self::Id>>(
+        &'a mut self,
+        ids: I,
+    ) -> GetManyOptionalUncheckedMut<'a, T, Self, I>
+    where
+        T: 'a,
+    {
+        GetManyOptionalUncheckedMut::new(self, ids)
+    }
 }
 
+/// A storage that keeps its components contiguously in memory, indexed by their id's.
+///
+/// Allows accessing whole columns as slices with [`query_slices!`](crate::query_slices).
+///
+pub trait SliceStorage<T>: Storage<T> {
+    /// Get all components as a slice, where the index of a component is its id.
+    fn as_slice(&self) -> &[T];
+    /// Get all components as a mutable slice, where the index of a component is its id.
+    fn as_mut_slice(&mut self) -> &mut [T];
+}
+
+/// Reorder the components of a dense storage in place, see [`Storage::reorder`].
+///
+/// Removing would swap the components around, so they are permuted all at once instead.
+pub(crate) fn reorder_dense<T>(values: &mut [T], order: &[usize]) -> Vec<usize> {
+    assert_eq!(order.len(), values.len(), "`order` must contain every id");
+    let mut pending = alloc::vec![false; order.len()];
+    for &id in order {
+        assert!(
+            !core::mem::replace(&mut pending[id], true),
+            "`order` must not contain duplicate id's"
+        );
+    }
+    // Follow each cycle of the permutation, moving the components into their places
+    for start in 0..order.len() {
+        let mut new_id = start;
+        while core::mem::replace(&mut pending[new_id], false) {
+            let id = order[new_id];
+            if id == start {
+                break;
+            }
+            values.swap(new_id, id);
+            new_id = id;
+        }
+    }
+    (0..order.len()).collect()
+}
+
+/// A family of storages that keep the components at the indices `0..len`,
+/// and remove them by moving the last one into their place (like [`Vec::swap_remove`]).
+///
+/// Required for `#[split(optional)]` fields, see [`BitsetStorage`](bitset::BitsetStorage).
+///
+/// # Safety
+/// The storages must insert new components at the id equal to their length,
+/// and removing the component at `id` must move the last one to `id`.
+///
+pub unsafe trait DenseFamily: StorageFamily<Id = usize> {}
+
+/// A family of storages that keep the id's of the other components when one is removed,
+/// so that the id's can be used as stable references to the entities.
+///
+/// Unlike a [`DenseFamily`], which moves the last component into the place of the removed one.
+/// Required by the [`Recorder`](crate::archetype::Recorder) and the [`Replica`](crate::archetype::Replica).
+///
+pub trait StableFamily: StorageFamily {}
+
 /// A family of storages for different component types.
 pub trait StorageFamily {
     /// Type of the identifier used for components/entities.
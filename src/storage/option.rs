@@ -0,0 +1,188 @@
+use crate::storage::{InsertError, StableFamily, Storage, StorageFamily};
+
+use alloc::vec::Vec;
+
+/// A storage adapter that keeps optional components inside another storage family,
+/// such that an entity may exist without its component being present.
+///
+/// Used for `#[split(nested)]` fields of type `Option<T>`,
+/// where all of the nested fields of `T` are stored in an [`OptionFamily`].
+pub struct OptionStorage<F: StorageFamily, T> {
+    inner: F::Storage<Option<T>>,
+}
+
+impl<F: StorageFamily, T> OptionStorage<F, T> {
+    /// Insert an absent component, returning its id.
+    pub fn insert_none(&mut self) -> F::Id {
+        self.inner.insert(None)
+    }
+
+    /// Insert an absent component, returning its id,
+    /// or an error if the storage rejects it, see [`Storage::try_insert`].
+    pub fn try_insert_none(&mut self) -> Result<F::Id, InsertError<()>> {
+        self.inner.try_insert(None).map_err(|err| err.map(|_| ()))
+    }
+
+    /// Insert an absent component with a given id.
+    /// Returns `false` if the id could not be used, see [`Storage::insert_with_id`].
+    pub fn insert_none_with_id(&mut self, id: F::Id) -> bool {
+        self.inner.insert_with_id(id, None).is_ok()
+    }
+
+    /// Replace the component with a given id, whether it is present or absent, returning the old one.
+    /// Returns `None` if the id is invalid.
+    pub fn replace(&mut self, id: F::Id, value: Option<T>) -> Option<Option<T>> {
+        self.inner
+            .get_mut(id)
+            .map(|component| core::mem::replace(component, value))
+    }
+
+    /// Remove a component with a given id, whether it is present or absent.
+    /// Returns `None` if the id is invalid.
+    pub fn take(&mut self, id: F::Id) -> Option<Option<T>> {
+        self.inner.remove(id)
+    }
+
+    /// Remove all components, whether they are present or absent,
+    /// returning an iterator over them together with their id's.
+    pub fn drain_optional(&mut self) -> <F::Storage<Option<T>> as Storage<Option<T>>>::Drain {
+        self.inner.drain()
+    }
+}
+
+impl<F: StorageFamily, T> Default for OptionStorage<F, T> {
+    fn default() -> Self {
+        Self {
+            inner: Default::default(),
+        }
+    }
+}
+
+impl<F: StorageFamily, T> Clone for OptionStorage<F, T>
+where
+    F::Storage<Option<T>>: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+unsafe impl<F: StorageFamily, T> Storage<T> for OptionStorage<F, T> {
+    type Family = OptionFamily<F>;
+    type Id = F::Id;
+    type IdsIter<'a>
+        = OptionIds<'a, F, T>
+    where
+        Self: 'a;
+    type Drain = OptionDrain<F, T>;
+    fn ids(&self) -> Self::IdsIter<'_> {
+        // SAFETY: a subset of the inner storage's id's
+        OptionIds {
+            storage: &self.inner,
+            ids: self.inner.ids(),
+        }
+    }
+    fn insert(&mut self, value: T) -> Self::Id {
+        self.inner.insert(Some(value))
+    }
+    fn try_insert(&mut self, value: T) -> Result<Self::Id, InsertError<T>> {
+        self.inner
+            .try_insert(Some(value))
+            .map_err(|err| err.map(|value| value.expect("the value was present")))
+    }
+    fn get(&self, id: Self::Id) -> Option<&T> {
+        self.inner.get(id)?.as_ref()
+    }
+    fn get_mut(&mut self, id: Self::Id) -> Option<&mut T> {
+        self.inner.get_mut(id)?.as_mut()
+    }
+    fn remove(&mut self, id: Self::Id) -> Option<T> {
+        self.inner.remove(id).flatten()
+    }
+    fn drain(&mut self) -> Self::Drain {
+        OptionDrain {
+            inner: self.inner.drain(),
+        }
+    }
+    fn drain_take(drain: &mut Self::Drain, id: Self::Id) -> Option<T> {
+        // NOTE: the other columns skip the absent components too,
+        // so the ones that follow the order of the drain take the next present one
+        loop {
+            if let Some(value) = F::Storage::<Option<T>>::drain_take(&mut drain.inner, id)? {
+                return Some(value);
+            }
+        }
+    }
+    fn insert_with_id(&mut self, id: Self::Id, value: T) -> Result<(), T> {
+        self.inner
+            .insert_with_id(id, Some(value))
+            .map_err(|value| value.expect("the value was present"))
+    }
+    fn reorder(&mut self, order: &[Self::Id]) -> Vec<Self::Id> {
+        // NOTE: `order` contains the absent entries as well
+        self.inner.reorder(order)
+    }
+}
+
+/// Keep only the present components when draining.
+pub(super) fn present<Id, T>((id, value): (Id, Option<T>)) -> Option<(Id, T)> {
+    value.map(|value| (id, value))
+}
+
+/// An owning iterator over the present components of an [`OptionStorage`], created by [`Storage::drain`].
+pub struct OptionDrain<F: StorageFamily, T> {
+    inner: <F::Storage<Option<T>> as Storage<Option<T>>>::Drain,
+}
+
+impl<F: StorageFamily, T> Iterator for OptionDrain<F, T> {
+    type Item = (F::Id, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.by_ref().find_map(present)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.inner.size_hint().1)
+    }
+}
+
+/// Iterator over the id's of the present components in an [`OptionStorage`].
+pub struct OptionIds<'a, F: StorageFamily, T: 'a> {
+    storage: &'a F::Storage<Option<T>>,
+    ids: <F::Storage<Option<T>> as Storage<Option<T>>>::IdsIter<'a>,
+}
+
+impl<F: StorageFamily, T> Clone for OptionIds<'_, F, T> {
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage,
+            ids: self.ids.clone(),
+        }
+    }
+}
+
+impl<F: StorageFamily, T> Iterator for OptionIds<'_, F, T> {
+    type Item = F::Id;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.ids
+            .by_ref()
+            .find(|&id| matches!(self.storage.get(id), Some(Some(_))))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.ids.size_hint().1)
+    }
+}
+
+/// Family of [`OptionStorage<F, T>`] storages.
+pub struct OptionFamily<F: StorageFamily>(core::marker::PhantomData<F>);
+
+impl<F: StorageFamily> StorageFamily for OptionFamily<F> {
+    type Id = F::Id;
+    type Storage<T> = OptionStorage<F, T>;
+}
+
+impl<F: StableFamily> StableFamily for OptionFamily<F> {}
@@ -5,15 +5,28 @@ use super::*;
 unsafe impl<T> Storage<T> for Vec<T> {
     type Family = VecFamily;
     type Id = usize;
+    type IdsIter<'a>
+        = core::ops::Range<usize>
+    where
+        T: 'a;
+    type Drain = core::iter::Enumerate<alloc::vec::IntoIter<T>>;
     fn insert(&mut self, value: T) -> Self::Id {
         let id = self.len();
         self.push(value);
         id
     }
-    fn ids(&self) -> impl Iterator<Item = Self::Id> + Clone {
+    fn ids(&self) -> Self::IdsIter<'_> {
         // SAFETY: guaranteed validity and uniqueness
         0..self.len()
     }
+    fn insert_with_id(&mut self, id: Self::Id, value: T) -> Result<(), T> {
+        // NOTE: the id's are indices, so only the next one is available
+        if id != self.len() {
+            return Err(value);
+        }
+        self.push(value);
+        Ok(())
+    }
     fn get(&self, id: Self::Id) -> Option<&T> {
         self.as_slice().get(id)
     }
@@ -23,18 +36,21 @@ unsafe impl<T> Storage<T> for Vec<T> {
     fn remove(&mut self, id: Self::Id) -> Option<T> {
         (id < self.len()).then(|| self.swap_remove(id))
     }
-    #[cfg(feature = "query_mut")]
-    unsafe fn get_many_unchecked_mut<'a>(
-        &'a mut self,
-        ids: impl Iterator<Item = Self::Id>,
-    ) -> impl Iterator<Item = &'a mut T>
-    where
-        T: 'a,
-    {
-        ids.map(move |i| {
-            let r = self.get_mut(i).expect("invalid id: entry absent");
-            unsafe { &mut *(r as *mut T) }
-        })
+    fn drain(&mut self) -> Self::Drain {
+        // NOTE: the id's are indices, so they are yielded in order
+        core::mem::take(self).into_iter().enumerate()
+    }
+    fn reorder(&mut self, order: &[Self::Id]) -> Vec<Self::Id> {
+        reorder_dense(self, order)
+    }
+}
+
+impl<T> SliceStorage<T> for Vec<T> {
+    fn as_slice(&self) -> &[T] {
+        self
+    }
+    fn as_mut_slice(&mut self) -> &mut [T] {
+        self
     }
 }
 
@@ -46,6 +62,9 @@ impl StorageFamily for VecFamily {
     type Storage<T> = Vec<T>;
 }
 
+// SAFETY: the id's are indices, and removal uses `swap_remove`
+unsafe impl DenseFamily for VecFamily {}
+
 impl<T: SplitFields<VecFamily>> StructOfAble for Vec<T> {
     type Struct = T;
     type Family = VecFamily;
@@ -0,0 +1,14 @@
+use darling::export::syn::{self, ext::IdentExt};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+/// The type-level name of a field, i.e. a list of `Char`'s: `(Char<'a'>, (Char<'b'>, ()))`.
+pub fn field_name_type(name: &syn::Ident) -> TokenStream {
+    name.unraw()
+        .to_string()
+        .chars()
+        .rev()
+        .fold(quote! { () }, |tail, c| {
+            quote! { (::stecs::archetype::Char<#c>, #tail) }
+        })
+}
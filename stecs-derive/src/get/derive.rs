@@ -16,7 +16,7 @@ impl StorageGetOpts {
         // }
 
         let (fields, constructor) = self.image.prepare_fields_constructor();
-        let mut get_fields = constructor;
+        let mut get_fields = quote! { Some(#constructor) };
 
         let storage = &self.struct_of;
         let id = &self.id;
@@ -35,23 +35,22 @@ impl StorageGetOpts {
                         #get_fields
                     }
                 },
-                Optic::Access { component, .. } => {
-                    if component.is_prism() {
-                        // Option<Option<T>>
-                        quote! {
-                            match #access {
-                                None => None,
-                                Some(None) => None,
-                                Some(Some(#name)) => { #get_fields }
-                            }
+                optic if optic.is_nested_prism() => {
+                    // Option<Option<T>>
+                    quote! {
+                        match #access {
+                            None => None,
+                            Some(None) => None,
+                            Some(Some(#name)) => { #get_fields }
                         }
-                    } else {
-                        // Option<T>
-                        quote! {
-                            match #access {
-                                None => None,
-                                Some(#name) => { #get_fields }
-                            }
+                    }
+                }
+                _ => {
+                    // Option<T>
+                    quote! {
+                        match #access {
+                            None => None,
+                            Some(#name) => { #get_fields }
                         }
                     }
                 }
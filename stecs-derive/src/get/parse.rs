@@ -3,7 +3,9 @@ use super::types::*;
 use crate::optic::{Optic, OpticComponent, OpticStorage};
 
 use darling::export::syn::{
-    self, braced, parenthesized,
+    self, braced,
+    ext::IdentExt,
+    parenthesized,
     parse::{Parse, ParseStream},
     punctuated::Punctuated,
 };
@@ -77,7 +79,8 @@ impl Parse for StructFieldOpts {
                 is_mut = true;
             }
 
-            input.parse::<Optic>()?
+            let optic = input.parse::<Optic>()?;
+            parse_joins(input, optic, is_mut)?
         } else {
             // NOTE: `id` is treated specially to get the id of the entity,
             // so it is not allowed as a field inside Archetype's
@@ -131,6 +134,36 @@ impl Parse for TupleFieldOpts {
             is_mut = true;
         }
         let optic: Optic = input.parse()?;
+        let optic = parse_joins(input, optic, is_mut)?;
         Ok(Self { is_mut, optic })
     }
 }
+
+// Joins follow the id's into other storages
+// target -> world.units.pos
+// target.Get.Some -> world.units.target -> world.units.pos
+
+fn parse_joins(input: ParseStream, mut optic: Optic, is_mut: bool) -> syn::Result<Optic> {
+    while let Some(arrow) = input.parse::<Option<syn::Token![->]>>()? {
+        if is_mut {
+            return Err(syn::Error::new_spanned(
+                arrow,
+                "joined components can only be accessed immutably",
+            ));
+        }
+        // NOTE: parse a field access chain manually,
+        // since a general expression would consume the following `->` as a binary minus
+        let root = input.call(syn::Ident::parse_any)?;
+        let mut target: syn::Expr = syn::parse_quote! { #root };
+        while input.peek(syn::Token![.]) {
+            input.parse::<syn::Token![.]>()?;
+            let member: syn::Member = input.parse()?;
+            target = syn::parse_quote! { #target.#member };
+        }
+        optic = Optic::Join {
+            optic: Box::new(optic),
+            target,
+        };
+    }
+    Ok(optic)
+}
@@ -1,6 +1,6 @@
 use crate::optic::Optic;
 
-use darling::export::syn::{self, punctuated::Punctuated};
+use darling::export::syn::{self, ext::IdentExt, punctuated::Punctuated};
 use proc_macro2::TokenStream;
 use quote::quote;
 
@@ -62,7 +62,7 @@ impl ImageOpts {
                         FieldName {
                             original: field.name.clone(),
                             mangled: syn::Ident::new(
-                                &format!("__{}", field.name),
+                                &format!("__{}", field.name.unraw()),
                                 proc_macro2::Span::call_site(),
                             ),
                         },
@@ -95,11 +95,11 @@ impl ImageOpts {
                 let fields = fields
                     .iter()
                     .map(|(FieldName { original, mangled }, _, _)| quote! { #original: #mangled });
-                quote! { Some(#ident { #(#fields),* }) }
+                quote! { #ident { #(#fields),* } }
             }
             ImageOpts::Tuple { .. } => {
                 let fields = fields.iter().map(|(name, _, _)| &name.mangled);
-                quote! { Some(( #(#fields),* )) }
+                quote! { ( #(#fields),* ) }
             }
         };
 
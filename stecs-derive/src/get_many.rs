@@ -0,0 +1,80 @@
+use crate::{
+    get::ImageOpts,
+    query::{QueryArchetype, QueryOpts},
+};
+
+use darling::export::syn::{
+    self,
+    parse::{Parse, ParseStream},
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+#[derive(Debug)]
+pub struct GetManyOpts {
+    /// The structure of storages to query components from.
+    struct_of: syn::Expr,
+    /// Id's of the entities to access.
+    ids: syn::ExprArray,
+    /// The image (struct or tuple) to collect the components into.
+    image: ImageOpts,
+}
+
+// get_many!(units, [a, b], { pos, tick })
+
+impl Parse for GetManyOpts {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let struct_of: syn::Expr = input.parse()?;
+        let _: syn::Token![,] = input.parse()?;
+
+        let ids: syn::ExprArray = input.parse()?;
+        let _: syn::Token![,] = input.parse()?;
+
+        let image: ImageOpts = input.parse()?;
+
+        Ok(Self {
+            struct_of,
+            ids,
+            image,
+        })
+    }
+}
+
+impl GetManyOpts {
+    pub fn get_many(self) -> TokenStream {
+        let n = self.ids.elems.len();
+        let struct_of = &self.struct_of;
+        let ids_name = quote! { __IDS }; // NOTE: mangled to avoid conflicts
+        let ids = self.ids;
+        let query = QueryOpts::new(
+            vec![QueryArchetype {
+                struct_of: self.struct_of.clone(),
+                ids: Some(syn::parse_quote! { #ids_name }),
+                checked_ids: true,
+                generic: false,
+            }],
+            self.image,
+        )
+        .query();
+
+        // NOTE: components filtered out by the query (e.g. with `.Some`) make the whole result `None`
+        quote! {{
+            let #ids_name = #ids;
+            let __valid = {
+                use ::stecs::__private::StorageExt as _;
+                #struct_of.ids.are_valid_unique(&#ids_name)
+            };
+            if __valid {
+                let mut __query = #query;
+                let __items: [_; #n] = ::core::array::from_fn(|_| __query.next());
+                if __items.iter().all(Option::is_some) {
+                    Some(__items.map(Option::unwrap))
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        }}
+    }
+}
@@ -3,10 +3,14 @@ use darling::{
     FromDeriveInput,
 };
 
+mod field;
 mod get;
+mod get_many;
 mod optic;
 mod query;
+mod query_slices;
 mod split;
+mod view;
 
 #[proc_macro_derive(SplitFields, attributes(split))]
 pub fn derive_split_fields(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
@@ -17,14 +21,41 @@ pub fn derive_split_fields(input: proc_macro::TokenStream) -> proc_macro::TokenS
     }
 }
 
+#[proc_macro_derive(Query, attributes(query))]
+pub fn derive_query(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input: syn::DeriveInput = syn::parse_macro_input!(input);
+    match view::ViewOpts::from_derive_input(&input) {
+        Ok(input) => input.derive().into(),
+        Err(e) => e.write_errors().into(),
+    }
+}
+
 #[proc_macro]
 pub fn storage_query(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(tokens as query::QueryOpts);
     input.query().into()
 }
 
+#[proc_macro]
+pub fn storage_query_slices(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(tokens as query_slices::QuerySlicesOpts);
+    input.query_slices().into()
+}
+
 #[proc_macro]
 pub fn storage_get(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(tokens as get::StorageGetOpts);
     input.get().into()
 }
+
+#[proc_macro]
+pub fn storage_get_many(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(tokens as get_many::GetManyOpts);
+    input.get_many().into()
+}
+
+#[proc_macro]
+pub fn field_name(tokens: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let name = parse_macro_input!(tokens as syn::Ident);
+    field::field_name_type(&name).into()
+}
@@ -9,6 +9,11 @@ pub enum Optic {
         storage: OpticStorage,
         component: OpticComponent,
     },
+    /// Follow the id (accessed by the inner optic) into the `target` storage.
+    Join {
+        optic: Box<Optic>,
+        target: syn::Expr,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -18,6 +23,8 @@ pub enum OpticStorage {
         name: syn::Ident,
         optic: Box<OpticStorage>,
     },
+    /// The storage is optional (e.g. a nested `Option<T>`), so the component may be absent.
+    Some(Box<OpticStorage>),
 }
 
 #[derive(Debug, Clone)]
@@ -28,9 +35,31 @@ pub enum OpticComponent {
         optic: Box<OpticComponent>,
     },
     Some(Box<OpticComponent>),
+    /// The component is a `bool` (e.g. a tag), and only the entities where it is `true` are accessed.
+    True(Box<OpticComponent>),
 }
 
 impl Optic {
+    /// Whether this optic is a prism (as opposed to being a lens),
+    /// i.e. whether the component may be absent for a valid entity.
+    pub fn is_prism(&self) -> bool {
+        match self {
+            Optic::GetId => false,
+            Optic::Access { storage, component } => storage.is_prism() || component.is_prism(),
+            Optic::Join { .. } => true,
+        }
+    }
+
+    /// Whether the immutable access returns an `Option<Option<T>>`
+    /// (as opposed to an `Option<T>`).
+    pub fn is_nested_prism(&self) -> bool {
+        match self {
+            Optic::GetId => false,
+            Optic::Access { component, .. } => component.is_prism(),
+            Optic::Join { .. } => true,
+        }
+    }
+
     /// Access the target component immutably.
     pub fn access(&self, id: TokenStream, archetype: TokenStream) -> TokenStream {
         self.access_impl(false, id, archetype)
@@ -44,22 +73,55 @@ impl Optic {
     fn access_impl(&self, is_mut: bool, id: TokenStream, archetype: TokenStream) -> TokenStream {
         match self {
             Optic::GetId => id,
-            Optic::Access { storage, component } => {
-                let storage = storage.access(archetype);
+            Optic::Join { optic, target } => {
+                assert!(!is_mut, "joined components can only be accessed immutably");
+                let inner = optic.access(id, archetype);
+                let target_name = quote! { __target };
+                match &**optic {
+                    Optic::GetId => quote! { Some(#target.get(#inner)) },
+                    optic if optic.is_nested_prism() => quote! {
+                        match #inner {
+                            None => None,
+                            Some(None) => Some(None),
+                            Some(Some(#target_name)) => Some(#target.get(*#target_name)),
+                        }
+                    },
+                    _ => quote! {
+                        match #inner {
+                            None => None,
+                            Some(#target_name) => Some(#target.get(*#target_name)),
+                        }
+                    },
+                }
+            }
+            Optic::Access {
+                storage: storage_optic,
+                component,
+            } => {
+                let storage = storage_optic.access(archetype);
 
-                let getter = if is_mut {
-                    quote! { get_mut }
-                } else {
-                    quote! { get }
+                // NOTE: optional storages return the absent components too, unless accessed with `.Some`,
+                // and tags are accessed mutably through a proxy, see `StorageExt::query_get`
+                let getter = match (storage_optic.is_prism(), is_mut) {
+                    (true, false) => quote! { get },
+                    (true, true) => quote! { get_mut },
+                    (false, false) => quote! { query_get },
+                    (false, true) => quote! { query_get_mut },
+                };
+                let get = quote! {
+                    {
+                        use ::stecs::{__private::StorageExt as _, storage::Storage as _};
+                        #storage.#getter(#id)
+                    }
                 };
 
                 if component.is_identity() {
-                    quote! { #storage.#getter(#id) }
+                    get
                 } else {
                     let value_name = quote! { __value };
                     let access = component.access_impl(is_mut, quote! { #value_name });
                     quote! {
-                        match #storage.#getter(#id) {
+                        match #get {
                             None => None,
                             Some(#value_name) => { Some(#access) }
                         }
@@ -70,23 +132,60 @@ impl Optic {
     }
 
     /// Access many entities (identified by `ids`) mutably.
+    /// If the optic is a prism, or the storage is `optional`, the values are optional (`None` for absent components).
     #[cfg(feature = "query_mut")]
-    pub fn access_many_mut(&self, ids: TokenStream, archetype: TokenStream) -> TokenStream {
+    pub fn access_many_mut(
+        &self,
+        optional: bool,
+        ids: TokenStream,
+        archetype: TokenStream,
+    ) -> TokenStream {
         match self {
             Optic::GetId => ids,
-            Optic::Access { storage, component } => {
-                let storage = storage.access(archetype);
+            Optic::Join { .. } => panic!("joined components can only be accessed immutably"),
+            Optic::Access {
+                storage: storage_optic,
+                component,
+            } => {
+                let storage = storage_optic.access(archetype);
 
                 let value_name = quote! { __value };
-                let access = if component.is_identity() {
-                    quote! {}
+                if optional || storage_optic.is_prism() {
+                    // Components in an optional storage may be absent
+                    let access = if component.is_identity() {
+                        quote! {}
+                    } else {
+                        let access = component.access_impl(true, quote! { #value_name });
+                        let access = if component.is_prism() {
+                            access
+                        } else {
+                            quote! { Some(#access) }
+                        };
+                        quote! {
+                            .map(|#value_name| match #value_name {
+                                None => None,
+                                Some(#value_name) => { #access }
+                            })
+                        }
+                    };
+
+                    quote! {
+                        unsafe { #storage.get_many_optional_unchecked_mut(#ids) } #access
+                    }
                 } else {
-                    let access = component.access_impl(true, quote! { #value_name });
-                    quote! { .map(|#value_name| #access) }
-                };
+                    let access = if component.is_identity() {
+                        quote! {}
+                    } else {
+                        let access = component.access_impl(true, quote! { #value_name });
+                        quote! { .map(|#value_name| #access) }
+                    };
 
-                quote! {
-                    unsafe { #storage.get_many_unchecked_mut(#ids) } #access
+                    quote! {
+                        {
+                            use ::stecs::__private::StorageExt as _;
+                            unsafe { #storage.query_get_many_unchecked_mut(#ids) } #access
+                        }
+                    }
                 }
             }
         }
@@ -98,6 +197,36 @@ impl OpticStorage {
         match self {
             OpticStorage::Identity => archetype,
             OpticStorage::Field { name, optic } => optic.access(quote! { #archetype.#name }),
+            // NOTE: optional storages are accessed the same way, but may have absent entries
+            OpticStorage::Some(optic) => optic.access(archetype),
+        }
+    }
+
+    /// The path to the nested storage containing this one (e.g. `shield` for `shield.durability`),
+    /// unless it is a top-level storage or is explicitly optional.
+    ///
+    /// Nested storages may be optional (e.g. a nested `Option<T>`) without a `.Some`,
+    /// in which case only the entities present in the nested storage's `ids` have the component.
+    pub fn nested_prefix(&self) -> Option<Vec<syn::Ident>> {
+        if self.is_prism() {
+            return None;
+        }
+        let mut names = vec![];
+        let mut storage = self;
+        while let OpticStorage::Field { name, optic } = storage {
+            names.push(name.clone());
+            storage = optic;
+        }
+        names.pop();
+        (!names.is_empty()).then_some(names)
+    }
+
+    /// Whether the storage is optional, i.e. may have no component for a valid entity.
+    pub fn is_prism(&self) -> bool {
+        match self {
+            OpticStorage::Identity => false,
+            OpticStorage::Field { optic, .. } => optic.is_prism(),
+            OpticStorage::Some(_) => true,
         }
     }
 }
@@ -109,6 +238,7 @@ impl OpticComponent {
             OpticComponent::Identity => true,
             OpticComponent::Field { .. } => false,
             OpticComponent::Some(_) => false,
+            OpticComponent::True(_) => false,
         }
     }
 
@@ -119,37 +249,69 @@ impl OpticComponent {
             OpticComponent::Identity => false,
             OpticComponent::Field { optic, .. } => optic.is_prism(),
             OpticComponent::Some(_) => true,
+            OpticComponent::True(_) => true,
         }
     }
 
+    /// Access the component, given the `entity` reference returned by the storage.
     fn access_impl(&self, is_mut: bool, entity: TokenStream) -> TokenStream {
+        self.access_inner(is_mut, true, entity)
+    }
+
+    /// Access the component, given a reference (if `is_ref`) or a place expression.
+    fn access_inner(&self, is_mut: bool, is_ref: bool, entity: TokenStream) -> TokenStream {
         match self {
             OpticComponent::Identity => entity,
             OpticComponent::Field { name, optic } => {
-                optic.access_impl(is_mut, quote! { #entity.#name })
+                optic.access_inner(is_mut, false, quote! { #entity.#name })
             }
             OpticComponent::Some(optic) => {
                 let value_name = quote! { __value };
-                let tail = optic.access_impl(is_mut, quote! { #value_name });
+                let tail = optic.access_inner(is_mut, true, quote! { #value_name });
                 let tail = if optic.is_prism() {
                     tail
                 } else {
                     quote! { Some(#tail) }
                 };
 
-                let convert = if is_mut {
-                    quote! { as_mut() }
+                // NOTE: references are matched as they are, since the storage may return
+                // either `&Option<T>` or `Option<&T>` (e.g. optional components in a bitset)
+                let convert = if is_ref {
+                    quote! {}
+                } else if is_mut {
+                    quote! { .as_mut() }
                 } else {
-                    quote! { as_ref() }
+                    quote! { .as_ref() }
                 };
 
                 quote! {
-                    match #entity.#convert {
+                    match #entity #convert {
                         None => None,
                         Some(#value_name) => { #tail }
                     }
                 }
             }
+            OpticComponent::True(optic) => {
+                let tail = optic.access_inner(is_mut, is_ref, entity.clone());
+                let tail = if optic.is_prism() {
+                    tail
+                } else {
+                    quote! { Some(#tail) }
+                };
+
+                // NOTE: the reference may be a proxy, e.g. a `TagMut`
+                let deref = if is_ref {
+                    quote! { * }
+                } else {
+                    quote! {}
+                };
+                quote! {
+                    match #deref #entity {
+                        false => None,
+                        true => #tail,
+                    }
+                }
+            }
         }
     }
 }
@@ -160,6 +322,7 @@ enum OpticPart {
     // Id,
     GetId,
     Some,
+    True,
     Field(syn::Ident),
     Get,
 }
@@ -208,12 +371,12 @@ impl Parse for Optic {
                     get_id = true;
                     storage
                 }
-                OpticPart::Some => {
-                    // TODO: maybe not (optional storages?)
+                OpticPart::Some => OpticStorage::Some(Box::new(storage)),
+                OpticPart::True => {
                     return Err(syn::Error::new_spanned(
                         token,
-                        "`Some` may only occur after `Get`",
-                    ));
+                        "`True` can only be used on components, i.e. after `Get`",
+                    ))
                 }
                 OpticPart::Field(name) => OpticStorage::Field {
                     name: name.clone(),
@@ -246,6 +409,7 @@ impl Parse for Optic {
                     ));
                 }
                 OpticPart::Some => OpticComponent::Some(Box::new(component)),
+                OpticPart::True => OpticComponent::True(Box::new(component)),
                 OpticPart::Field(name) => OpticComponent::Field {
                     name: name.clone(),
                     optic: Box::new(component),
@@ -266,10 +430,13 @@ impl Parse for Optic {
 impl Parse for OpticPartToken {
     fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
         let ident: syn::Ident = input.parse()?;
+        // NOTE: a raw identifier (e.g. `r#id`) always refers to a field,
+        // which allows escaping the `id` keyword
         let part = match ident.to_string().as_str() {
             // "_id" => Self::Id,
             "id" => OpticPart::GetId,
             "Some" => OpticPart::Some,
+            "True" => OpticPart::True,
             "Get" => OpticPart::Get,
             _ => OpticPart::Field(ident.clone()),
         };
@@ -1,4 +1,7 @@
-use crate::{get::ImageOpts, optic::Optic};
+use crate::{
+    get::{FieldName, ImageOpts},
+    optic::{Optic, OpticStorage},
+};
 
 use darling::export::syn::{
     self,
@@ -10,29 +13,51 @@ use quote::quote;
 #[derive(Debug)]
 pub struct QueryOpts {
     /// The structure(s) of storages to query components from.
-    struct_ofs: Vec<syn::Expr>,
+    struct_ofs: Vec<QueryArchetype>,
     /// The image (struct or tuple) to collect the components into.
     image: ImageOpts,
 }
 
+#[derive(Debug)]
+pub struct QueryArchetype {
+    /// The structure of storages to query components from.
+    pub struct_of: syn::Expr,
+    /// The subset of id's to query, if specified.
+    pub ids: Option<syn::Expr>,
+    /// Whether the `ids` are already known to be valid and unique.
+    pub checked_ids: bool,
+    /// Whether the archetype is generic (`impl archetype`),
+    /// so its fields are accessed through `HasField`.
+    pub generic: bool,
+}
+
 // query!(units, { pos, tick })
 
 impl Parse for QueryOpts {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let _span_start = input.span();
 
-        let struct_ofs = if input.peek(syn::token::Bracket) {
+        // A generic archetype, accessed through `HasField`
+        // query!(impl archetype, ...)
+        let generic = input.parse::<Option<syn::Token![impl]>>()?.is_some();
+
+        let struct_ofs = if !generic && input.peek(syn::token::Bracket) {
             // Parse an array of struct_of's
             // [a, b, c]
             let list;
             syn::bracketed!(list in input);
             let items =
-                syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated(&list)?;
+                syn::punctuated::Punctuated::<QueryArchetype, syn::Token![,]>::parse_terminated(
+                    &list,
+                )?;
             items.into_iter().collect()
         } else {
             // Parse a single struct_of
-            let struct_of: syn::Expr = input.parse()?;
-            vec![struct_of]
+            let struct_of: QueryArchetype = input.parse()?;
+            vec![QueryArchetype {
+                generic,
+                ..struct_of
+            }]
         };
 
         if struct_ofs.is_empty() {
@@ -65,48 +90,158 @@ impl Parse for QueryOpts {
     }
 }
 
+// `where` selects a subset of id's
+// units where ids
+
+impl Parse for QueryArchetype {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let struct_of: syn::Expr = input.parse()?;
+        let ids = match input.parse::<Option<syn::Token![where]>>()? {
+            Some(_) => Some(input.parse()?),
+            None => None,
+        };
+        Ok(Self {
+            struct_of,
+            ids,
+            checked_ids: false,
+            generic: false,
+        })
+    }
+}
+
+impl From<syn::Expr> for QueryArchetype {
+    fn from(struct_of: syn::Expr) -> Self {
+        Self {
+            struct_of,
+            ids: None,
+            checked_ids: false,
+            generic: false,
+        }
+    }
+}
+
 impl QueryOpts {
+    pub fn new(struct_ofs: Vec<QueryArchetype>, image: ImageOpts) -> Self {
+        Self { struct_ofs, image }
+    }
+
     pub fn query(self) -> TokenStream {
         let (fields, constructor) = self.image.prepare_fields_constructor();
         if fields.is_empty() {
-            return quote! { ::std::iter::empty() };
+            return quote! { ::core::iter::empty() };
         }
 
         let mut result = vec![];
-        for storage in &self.struct_ofs {
+        for QueryArchetype {
+            struct_of: storage,
+            ids,
+            checked_ids,
+            generic,
+        } in &self.struct_ofs
+        {
             let mut query = vec![];
 
+            let storage = if *generic {
+                // Borrow the used fields into a local struct, so that they are accessed by name
+                let fields_name = quote! { __FIELDS }; // NOTE: mangled to avoid conflicts
+                query.push(generic_fields(storage, &fields, &fields_name));
+                fields_name
+            } else {
+                quote! { #storage }
+            };
+
             // Get each field
             let id_expr = quote! { __ID }; // NOTE: mangled to avoid conflicts
-            let ids_expr = quote! { #storage.ids.ids() };
-            query.extend(fields.iter().map(|(name, is_mut, optic)| {
-                let name = &name.mangled;
-                if *is_mut {
-                    #[cfg(feature = "query_mut")]
-                    {
-                        let component =
-                            optic.access_many_mut(ids_expr.clone(), quote! { #storage });
-                        quote! { let #name = #component; }
+            let mut driver_prefix = None;
+            let ids_expr = match ids {
+                None => match driving_storage(&fields) {
+                    // NOTE: iterate only over the entities that have the optional component
+                    Some(driver) => {
+                        let driver = driver.access(quote! { #storage });
+                        quote! { #driver.ids() }
                     }
-
-                    #[cfg(not(feature = "query_mut"))]
-                    panic!(
-                        "The `query_mut` feature is disabled, so mutable queries are not supported"
-                    );
-                } else if matches!(optic, Optic::GetId) {
-                    quote! {
-                        let #name = #ids_expr;
-                    }
-                } else {
-                    let component = optic.access(id_expr.clone(), quote! { #storage });
-                    quote! {
-                        let #name = #ids_expr.map(|#id_expr| {
-                            let value = #component;
-                            value.expect("`id` must be valid")
+                    None => match fields.iter().find_map(|(_, _, optic)| nested_prefix(optic)) {
+                        // NOTE: a nested storage may be optional,
+                        // so iterate only over the entities present in it
+                        Some(prefix) => {
+                            let ids = quote! { #storage #(.#prefix)*.ids.ids() };
+                            driver_prefix = Some(prefix);
+                            ids
+                        }
+                        None => quote! { #storage.ids.ids() },
+                    },
+                },
+                Some(ids) => {
+                    // Check the id's for validity and uniqueness
+                    let ids_name = quote! { __IDS }; // NOTE: mangled to avoid conflicts
+                    if *checked_ids {
+                        query.push(quote! { let #ids_name = #ids; });
+                    } else {
+                        query.push(quote! {
+                            let #ids_name = {
+                                use ::stecs::__private::StorageExt as _;
+                                #storage.ids.valid_unique_ids(#ids)
+                            };
                         });
                     }
+                    quote! { #ids_name.clone().into_iter() }
                 }
-            }));
+            };
+            // Components in the other nested storages may be absent, since they may be optional
+            let optional = fields
+                .iter()
+                .map(|(_, _, optic)| {
+                    nested_prefix(optic).is_some_and(|prefix| Some(prefix) != driver_prefix)
+                })
+                .collect::<Vec<_>>();
+
+            query.extend(
+                fields
+                    .iter()
+                    .zip(&optional)
+                    .map(|((name, is_mut, optic), optional)| {
+                        let name = &name.mangled;
+                        if *is_mut {
+                            #[cfg(feature = "query_mut")]
+                            {
+                                let component = optic.access_many_mut(
+                                    *optional,
+                                    ids_expr.clone(),
+                                    quote! { #storage },
+                                );
+                                quote! { let #name = #component; }
+                            }
+
+                            #[cfg(not(feature = "query_mut"))]
+                            panic!(
+                        "The `query_mut` feature is disabled, so mutable queries are not supported"
+                    );
+                        } else if matches!(optic, Optic::GetId) {
+                            quote! {
+                                let #name = #ids_expr;
+                            }
+                        } else {
+                            let component = optic.access(id_expr.clone(), quote! { #storage });
+                            // NOTE: components inside optional storages may be absent,
+                            // otherwise the id's are valid
+                            let absent = *optional
+                                || matches!(optic, Optic::Access { storage, .. } if storage.is_prism());
+                            let unwrap = if optic.is_nested_prism() && absent {
+                                quote! { .flatten() }
+                            } else if absent {
+                                quote! {}
+                            } else {
+                                quote! { .expect("invalid id: entry absent") }
+                            };
+                            quote! {
+                                let #name = #ids_expr.map(|#id_expr| {
+                                    let value = #component;
+                                    value #unwrap
+                                });
+                            }
+                        }
+                    }),
+            );
 
             // Zip fields
             query.push(quote! {});
@@ -132,31 +267,35 @@ impl QueryOpts {
                 args = quote! { (#args, #name) };
             }
 
-            // Filter only values that are Some
-            let filtered = fields
+            if fields
                 .iter()
-                .map(|(name, _, optic)| {
-                    let optional = if let Optic::Access { component, .. } = optic {
-                        component.is_prism()
-                    } else {
-                        false
-                    };
-                    if optional {
+                .zip(&optional)
+                .any(|((_, _, optic), optional)| optic.is_prism() || *optional)
+            {
+                // Filter only values that are present
+                let filtered = fields
+                    .iter()
+                    .zip(&optional)
+                    .filter(|((_, _, optic), optional)| optic.is_prism() || **optional)
+                    .map(|((name, _, _), _)| {
                         let name = &name.mangled;
                         quote! { let #name = #name?; }
-                    } else {
-                        quote! {}
-                    }
-                })
-                .collect::<Vec<_>>();
+                    })
+                    .collect::<Vec<_>>();
 
-            // map
-            query.push(quote! {
-                .filter_map(|#args| {
-                    #(#filtered)*
-                    #constructor
-                })
-            });
+                query.push(quote! {
+                    .filter_map(|#args| {
+                        #(#filtered)*
+                        Some(#constructor)
+                    })
+                });
+            } else {
+                // NOTE: all components are present, so the query preserves
+                // `ExactSizeIterator` and `DoubleEndedIterator` of the id's
+                query.push(quote! {
+                    .map(|#args| #constructor)
+                });
+            }
 
             if result.is_empty() {
                 result.push(quote! { { #(#query)* } });
@@ -173,3 +312,101 @@ impl QueryOpts {
         }
     }
 }
+
+/// Borrow the fields of a generic archetype used in the query (and the id's) through `HasField`.
+fn generic_fields(
+    archetype: &syn::Expr,
+    fields: &[(FieldName, bool, Optic)],
+    fields_name: &TokenStream,
+) -> TokenStream {
+    fn root(optic: &Optic) -> Option<&syn::Ident> {
+        match optic {
+            Optic::GetId => None,
+            Optic::Access { storage, .. } => root_storage(storage),
+            Optic::Join { optic, .. } => root(optic),
+        }
+    }
+    fn root_storage(storage: &OpticStorage) -> Option<&syn::Ident> {
+        match storage {
+            OpticStorage::Identity => {
+                panic!("generic queries can only access the fields of the archetype")
+            }
+            OpticStorage::Field { name, .. } => Some(name),
+            OpticStorage::Some(optic) => root_storage(optic),
+        }
+    }
+
+    let mut names = vec![syn::Ident::new("ids", proc_macro2::Span::call_site())];
+    for (_, _, optic) in fields {
+        if let Some(name) = root(optic) {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    let types = (0..names.len())
+        .map(|i| syn::Ident::new(&format!("__T{i}"), proc_macro2::Span::call_site()))
+        .collect::<Vec<_>>();
+
+    let is_mut = fields.iter().any(|(_, is_mut, _)| *is_mut);
+    let value = if is_mut {
+        let field_names = names.iter().map(crate::field::field_name_type);
+        let values = (0..names.len()).map(syn::Index::from);
+        quote! {
+            {
+                let __fields = ::stecs::archetype::Fields::fields_mut::<(#(#field_names,)*)>(&mut *#archetype);
+                __Fields { #(#names: __fields.#values,)* }
+            }
+        }
+    } else {
+        let values = names.iter().map(|name| {
+            let field_name = crate::field::field_name_type(name);
+            quote! { ::stecs::archetype::HasField::<#field_name>::get_field(&*#archetype) }
+        });
+        quote! { __Fields { #(#names: #values,)* } }
+    };
+
+    let mutability = if is_mut {
+        quote! { mut }
+    } else {
+        quote! {}
+    };
+    quote! {
+        struct __Fields<#(#types),*> {
+            #(#names: #types,)*
+        }
+        let #mutability #fields_name = #value;
+    }
+}
+
+/// The path to the nested storage (possibly optional) containing the accessed storage.
+fn nested_prefix(optic: &Optic) -> Option<Vec<syn::Ident>> {
+    match optic {
+        Optic::Access { storage, .. } => storage.nested_prefix(),
+        _ => None,
+    }
+}
+
+/// Find an optional storage accessed immutably (e.g. `&damage.Some`),
+/// so that the query can iterate only over the id's present in it.
+fn driving_storage(fields: &[(FieldName, bool, Optic)]) -> Option<&OpticStorage> {
+    let path = |storage: &OpticStorage| storage.access(quote! {}).to_string();
+    let mut_paths: Vec<String> = fields
+        .iter()
+        .filter_map(|(_, is_mut, optic)| match optic {
+            Optic::Access { storage, .. } if *is_mut => Some(path(storage)),
+            _ => None,
+        })
+        .collect();
+    fields.iter().find_map(|(_, is_mut, optic)| match optic {
+        Optic::Access { storage, .. } if !is_mut && storage.is_prism() => {
+            // NOTE: the storage must not overlap with the mutably borrowed ones
+            let driver = path(storage);
+            let overlaps = mut_paths
+                .iter()
+                .any(|other| other.starts_with(&driver) || driver.starts_with(other.as_str()));
+            (!overlaps).then_some(storage)
+        }
+        _ => None,
+    })
+}
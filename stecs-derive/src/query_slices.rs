@@ -0,0 +1,82 @@
+use crate::{
+    get::ImageOpts,
+    optic::{Optic, OpticComponent},
+};
+
+use darling::export::syn::{
+    self,
+    parse::{Parse, ParseStream},
+};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+#[derive(Debug)]
+pub struct QuerySlicesOpts {
+    /// The structure of storages to query components from.
+    struct_of: syn::Expr,
+    /// The image (struct or tuple) to collect the slices into.
+    image: ImageOpts,
+}
+
+// query_slices!(units, (&mut pos, &vel))
+
+impl Parse for QuerySlicesOpts {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let struct_of: syn::Expr = input.parse()?;
+        let _: syn::Token![,] = input.parse()?;
+
+        let image: ImageOpts = input.parse()?;
+
+        Ok(Self { struct_of, image })
+    }
+}
+
+impl QuerySlicesOpts {
+    pub fn query_slices(self) -> TokenStream {
+        match self.query_slices_impl() {
+            Ok(tokens) => tokens,
+            Err(err) => err.to_compile_error(),
+        }
+    }
+
+    fn query_slices_impl(self) -> syn::Result<TokenStream> {
+        let (fields, constructor) = self.image.prepare_fields_constructor();
+
+        let struct_of = &self.struct_of;
+        let archetype = quote! { #struct_of };
+        let slices = fields
+            .iter()
+            .map(|(name, is_mut, optic)| {
+                let name = &name.mangled;
+                let storage = match optic {
+                    Optic::Access {
+                        storage,
+                        component: OpticComponent::Identity,
+                    } if !storage.is_prism() => storage.access(archetype.clone()),
+                    Optic::GetId => {
+                        let msg = "`id` cannot be queried as a slice, use the index in the slice";
+                        return Err(syn::Error::new(proc_macro2::Span::call_site(), msg));
+                    }
+                    _ => {
+                        let msg = "only whole (non-optional) storages can be queried as slices";
+                        return Err(syn::Error::new(proc_macro2::Span::call_site(), msg));
+                    }
+                };
+                let slice = if *is_mut {
+                    quote! { ::stecs::storage::SliceStorage::as_mut_slice(&mut #storage) }
+                } else {
+                    quote! { ::stecs::storage::SliceStorage::as_slice(&#storage) }
+                };
+                Ok(quote! {
+                    #[allow(non_snake_case)]
+                    let #name = #slice;
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        Ok(quote! {{
+            #(#slices)*
+            #constructor
+        }})
+    }
+}
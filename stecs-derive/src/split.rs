@@ -1,6 +1,6 @@
 use crate::syn;
 
-use darling::{ast, FromDeriveInput, FromField};
+use darling::{ast, export::syn::ext::IdentExt, FromDeriveInput, FromField};
 use proc_macro2::TokenStream;
 use quote::{quote, TokenStreamExt};
 
@@ -13,6 +13,11 @@ pub struct SplitOpts {
     generics: syn::Generics,
     debug: Option<()>,
     clone: Option<()>,
+    eq: Option<()>,
+    hash: Option<()>,
+    diff: Option<()>,
+    on_insert: Option<syn::Path>,
+    on_remove: Option<syn::Path>,
 }
 
 #[derive(FromField)]
@@ -21,6 +26,8 @@ struct FieldOpts {
     ident: Option<syn::Ident>,
     ty: syn::Type,
     nested: Option<()>,
+    optional: Option<()>,
+    tag: Option<()>,
 }
 
 struct Struct {
@@ -30,12 +37,34 @@ struct Struct {
     generics: syn::Generics,
     debug: bool,
     to_owned: bool,
+    eq: bool,
+    hash: bool,
+    diff: bool,
+    /// Called with the id and the `Ref` of each inserted entity.
+    on_insert: Option<syn::Path>,
+    /// Called with the id and the `Ref` of each entity before it is removed.
+    on_remove: Option<syn::Path>,
 }
 
 struct Field {
     name: syn::Ident,
     ty: syn::Type,
     nested: bool,
+    /// The inner type `T`, when a nested field is of type `Option<T>`.
+    optional: Option<syn::Type>,
+    /// The inner type `T`, when an `#[split(optional)]` field of type `Option<T>`
+    /// is stored in a [`BitsetStorage`](::stecs::storage::bitset::BitsetStorage).
+    presence: Option<syn::Type>,
+    /// Whether a `#[split(tag)]` field of type `bool`
+    /// is stored in a [`TagStorage`](::stecs::storage::bitset::TagStorage).
+    tag: bool,
+}
+
+impl Field {
+    /// Whether the component may be absent for a valid entity.
+    fn is_optional(&self) -> bool {
+        self.optional.is_some() || self.presence.is_some()
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -44,6 +73,16 @@ enum ParseError {
     NotAStruct,
     #[error("field has no name")]
     NamelessField,
+    #[error("field `{0}` is marked `#[split(optional)]` but is not of type `Option<T>`")]
+    NotAnOption(syn::Ident),
+    #[error("field `{0}` cannot be both `#[split(nested)]` and `#[split(optional)]`")]
+    NestedOptional(syn::Ident),
+    #[error("field `{0}` is marked `#[split(tag)]` but is not of type `bool`")]
+    NotABool(syn::Ident),
+    #[error("field `{0}` cannot be `#[split(tag)]` together with `#[split(nested)]` or `#[split(optional)]`")]
+    TagConflict(syn::Ident),
+    #[error("`#[split(diff)]` does not support nested fields, but `{0}` is nested")]
+    DiffNested(syn::Ident),
 }
 
 impl TryFrom<SplitOpts> for Struct {
@@ -59,13 +98,44 @@ impl TryFrom<SplitOpts> for Struct {
             .into_iter()
             .map(|field| {
                 let name = field.ident.ok_or(ParseError::NamelessField)?;
+                let nested = field.nested.is_some();
+                let optional = if nested {
+                    option_inner_type(&field.ty).cloned()
+                } else {
+                    None
+                };
+                let presence = match field.optional {
+                    None => None,
+                    Some(()) if nested => return Err(ParseError::NestedOptional(name)),
+                    Some(()) => match option_inner_type(&field.ty) {
+                        Some(ty) => Some(ty.clone()),
+                        None => return Err(ParseError::NotAnOption(name)),
+                    },
+                };
+                let tag = field.tag.is_some();
+                if tag {
+                    if nested || presence.is_some() {
+                        return Err(ParseError::TagConflict(name));
+                    }
+                    if !matches!(&field.ty, syn::Type::Path(path) if path.path.is_ident("bool")) {
+                        return Err(ParseError::NotABool(name));
+                    }
+                }
                 Ok(Field {
                     name,
                     ty: field.ty,
-                    nested: field.nested.is_some(),
+                    nested,
+                    optional,
+                    presence,
+                    tag,
                 })
             })
             .collect::<Result<Vec<Field>, ParseError>>()?;
+        if value.diff.is_some() {
+            if let Some(field) = fields.iter().find(|field| field.nested) {
+                return Err(ParseError::DiffNested(field.name.clone()));
+            }
+        }
         Ok(Self {
             name: value.ident,
             visibility: value.vis,
@@ -73,10 +143,33 @@ impl TryFrom<SplitOpts> for Struct {
             generics: value.generics,
             debug: value.debug.is_some(),
             to_owned: value.clone.is_some(),
+            eq: value.eq.is_some(),
+            hash: value.hash.is_some(),
+            diff: value.diff.is_some(),
+            on_insert: value.on_insert,
+            on_remove: value.on_remove,
         })
     }
 }
 
+/// Returns `T` if the type is (syntactically) `Option<T>`.
+fn option_inner_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        syn::GenericArgument::Type(ty) if args.args.len() == 1 => Some(ty),
+        _ => None,
+    }
+}
+
 impl SplitOpts {
     pub fn derive(self) -> TokenStream {
         let query = Struct::try_from(self).unwrap_or_else(|err| panic!("{err}"));
@@ -93,20 +186,24 @@ impl Struct {
             generics: struct_generics,
             debug: struct_debug,
             to_owned: struct_to_owned,
+            eq: struct_eq,
+            hash: struct_hash,
+            diff: struct_diff,
+            on_insert: struct_on_insert,
+            on_remove: struct_on_remove,
         } = self;
 
-        if struct_fields.iter().any(|field| field.name == "id") {
-            panic!(
-                "`id` is not allowed to be a field name, as it is used as a keyword inside queries"
-            );
-        }
-
         let struct_of_name = syn::Ident::new(
             &format!("{struct_name}StructOf"),
             proc_macro2::Span::call_site(),
         );
 
         let generic_family_name = quote! { __F }; // NOTE: mangled name to avoid conflicts
+        let is_dense = struct_fields
+            .iter()
+            .any(|field| field.presence.is_some() || field.tag);
+        let option_family_name =
+            quote! { ::stecs::storage::option::OptionFamily<#generic_family_name> };
         let (generics, generics_family, generics_use, generics_family_use) = {
             let params: Vec<_> = struct_generics.params.iter().collect();
             let params_use: Vec<_> = params
@@ -133,10 +230,13 @@ impl Struct {
                 .position(|param| !matches!(param, syn::GenericParam::Lifetime(_)))
                 .unwrap_or(params.len());
             let mut params_family: Vec<_> = params.iter().map(|param| quote! { #param}).collect();
-            params_family.insert(
-                i,
-                quote! { #generic_family_name: ::stecs::storage::StorageFamily },
-            );
+            // NOTE: bitset storages keep the components at the same indices as the family's storages
+            let family_bound = if is_dense {
+                quote! { ::stecs::storage::DenseFamily }
+            } else {
+                quote! { ::stecs::storage::StorageFamily }
+            };
+            params_family.insert(i, quote! { #generic_family_name: #family_bound });
 
             let mut params_family_use = params_use.clone();
             params_family_use.insert(i, quote! { #generic_family_name });
@@ -156,7 +256,7 @@ impl Struct {
                 syn::GenericParam::Lifetime(_) => quote! {},
                 syn::GenericParam::Type(param) => {
                     let name = &param.ident;
-                    quote! { #name: ::std::clone::Clone, }
+                    quote! { #name: ::core::clone::Clone, }
                 }
                 syn::GenericParam::Const(_) => quote! {},
             })
@@ -167,7 +267,15 @@ impl Struct {
                 .iter()
                 .map(|field| {
                     let name = &field.name;
-                    quote! { #name: self.#name.clone(), }
+                    if field.optional.is_some() {
+                        quote! { #name: self.#name.as_ref().map(|value| value.clone()), }
+                    } else if field.presence.is_some() {
+                        quote! { #name: self.#name.as_deref().cloned(), }
+                    } else if field.tag {
+                        quote! { #name: *self.#name, }
+                    } else {
+                        quote! { #name: self.#name.clone(), }
+                    }
                 })
                 .collect::<Vec<_>>();
 
@@ -184,6 +292,23 @@ impl Struct {
 
         let struct_ref_name =
             syn::Ident::new(&format!("{struct_name}Ref"), proc_macro2::Span::call_site());
+        let ref_derive = {
+            let mut derives = Vec::new();
+            if struct_debug {
+                derives.push(quote! { Debug });
+            }
+            if struct_eq {
+                derives.push(quote! { PartialEq });
+            }
+            if struct_hash {
+                derives.push(quote! { Hash });
+            }
+            if derives.is_empty() {
+                quote! {}
+            } else {
+                quote! { #[derive(#(#derives),*)] }
+            }
+        };
         let lifetime_ref_name = quote! { '__a }; // NOTE: mangled name to avoid conflicts
         let struct_ref = {
             let fields = struct_fields
@@ -191,7 +316,11 @@ impl Struct {
                 .map(|field| {
                     let name = &field.name;
                     let ty = &field.ty;
-                    let ty = if field.nested {
+                    let ty = if let Some(ty) = &field.optional {
+                        quote! { Option<<#ty as ::stecs::archetype::StructRef>::Ref<#lifetime_ref_name>> }
+                    } else if let Some(ty) = &field.presence {
+                        quote! { Option<&#lifetime_ref_name #ty> }
+                    } else if field.nested {
                         quote! { <#ty as ::stecs::archetype::StructRef>::Ref<#lifetime_ref_name> }
                     } else {
                         quote! { &#lifetime_ref_name #ty }
@@ -200,11 +329,7 @@ impl Struct {
                 })
                 .collect::<Vec<_>>();
 
-            let derive = if struct_debug {
-                quote! { #[derive(Debug)] }
-            } else {
-                quote! {}
-            };
+            let derive = &ref_derive;
 
             let struct_ref_doc = format!(
                 r#"Generated by `#[derive(SplitFields)]`.
@@ -222,6 +347,14 @@ This struct is a version `{struct_name}` that hold references to its fields."#
                     impl<#generics> #struct_ref_name<'_, #generics_use> {
                         #struct_to_owneded
                     }
+
+                    impl<#lifetime_ref_name, #generics> From<#struct_ref_name<#lifetime_ref_name, #generics_use>> for #struct_name<#generics_use>
+                    where #(#to_owned_constraints)*
+                    {
+                        fn from(value: #struct_ref_name<#lifetime_ref_name, #generics_use>) -> Self {
+                            value.clone()
+                        }
+                    }
                 }
             } else {
                 quote! {}
@@ -244,7 +377,14 @@ This struct is a version `{struct_name}` that hold references to its fields."#
                 .map(|field| {
                     let name = &field.name;
                     let ty = &field.ty;
-                    let ty = if field.nested {
+                    let ty = if let Some(ty) = &field.optional {
+                        quote! { Option<<#ty as ::stecs::archetype::StructRef>::RefMut<#lifetime_ref_name>> }
+                    } else if let Some(ty) = &field.presence {
+                        quote! { Option<&#lifetime_ref_name mut #ty> }
+                    } else if field.tag {
+                        // NOTE: tags are bits, so they are accessed through a proxy
+                        quote! { ::stecs::storage::bitset::TagMut<#lifetime_ref_name> }
+                    } else if field.nested {
                         quote! { <#ty as ::stecs::archetype::StructRef>::RefMut<#lifetime_ref_name> }
                     } else {
                         quote! { &#lifetime_ref_name mut #ty }
@@ -253,11 +393,7 @@ This struct is a version `{struct_name}` that hold references to its fields."#
                 })
                 .collect::<Vec<_>>();
 
-            let derive = if struct_debug {
-                quote! { #[derive(Debug)] }
-            } else {
-                quote! {}
-            };
+            let derive = &ref_derive;
 
             let struct_ref_mut_doc = format!(
                 r#"Generated by `#[derive(SplitFields)]`.
@@ -326,7 +462,13 @@ This struct is a version `{struct_name}` that hold mutable references to its fie
                 .map(|field| {
                     let name = &field.name;
                     let ty = &field.ty;
-                    let ty = if field.nested {
+                    let ty = if let Some(ty) = &field.optional {
+                        quote! { <#ty as ::stecs::archetype::SplitFields<#option_family_name>>::StructOf }
+                    } else if let Some(ty) = &field.presence {
+                        quote! { ::stecs::storage::bitset::BitsetStorage<#generic_family_name, #ty> }
+                    } else if field.tag {
+                        quote! { ::stecs::storage::bitset::TagStorage<#generic_family_name> }
+                    } else if field.nested {
                         quote! { <#ty as ::stecs::archetype::SplitFields<#generic_family_name>>::StructOf }
                     } else {
                         quote! { #generic_family_name::Storage<#ty> }
@@ -358,7 +500,13 @@ This struct is a version `{struct_name}` that each field in its own [Storage](::
                 .iter()
                 .map(|field| {
                     let ty = &field.ty;
-                    if field.nested {
+                    if let Some(ty) = &field.optional {
+                        quote! { <#ty as ::stecs::archetype::SplitFields<#option_family_name>>::StructOf: Clone }
+                    } else if let Some(ty) = &field.presence {
+                        quote! { ::stecs::storage::bitset::BitsetStorage<#generic_family_name, #ty>: Clone }
+                    } else if field.tag {
+                        quote! { ::stecs::storage::bitset::TagStorage<#generic_family_name>: Clone }
+                    } else if field.nested {
                         quote! { <#ty as ::stecs::archetype::SplitFields<#generic_family_name>>::StructOf: Clone }
                     } else {
                         quote! { #generic_family_name::Storage<#ty>: Clone }
@@ -390,6 +538,21 @@ This struct is a version `{struct_name}` that each field in its own [Storage](::
             }
         };
 
+        let id_name = quote! { __id }; // NOTE: mangled name to avoid conflicts with a field named `id`
+
+        let to_vec = if struct_to_owned {
+            quote! {
+                /// Clone all entities into a `Vec`, in the order of iteration.
+                pub fn to_vec(&self) -> ::stecs::__private::Vec<#struct_name<#generics_use>>
+                where #(#to_owned_constraints)*
+                {
+                    self.iter().map(|(_, item)| item.clone()).collect()
+                }
+            }
+        } else {
+            quote! {}
+        };
+
         let struct_of_impl = {
             let fields = struct_fields
                 .iter()
@@ -403,7 +566,13 @@ This struct is a version `{struct_name}` that each field in its own [Storage](::
                 .iter()
                 .map(|field| {
                     let name = &field.name;
-                    quote! { let #name = self.#name.get(id)?; }
+                    if field.optional.is_some() {
+                        quote! { let #name = self.#name.get(#id_name); }
+                    } else if field.presence.is_some() {
+                        quote! { let #name = self.#name.get_optional(#id_name)?; }
+                    } else {
+                        quote! { let #name = self.#name.get(#id_name)?; }
+                    }
                 })
                 .collect::<Vec<_>>();
             get.push(quote! {
@@ -416,7 +585,13 @@ This struct is a version `{struct_name}` that each field in its own [Storage](::
                 .iter()
                 .map(|field| {
                     let name = &field.name;
-                    quote! { let #name = self.#name.get_mut(id)?; }
+                    if field.optional.is_some() {
+                        quote! { let #name = self.#name.get_mut(#id_name); }
+                    } else if field.presence.is_some() {
+                        quote! { let #name = self.#name.get_optional_mut(#id_name)?; }
+                    } else {
+                        quote! { let #name = self.#name.get_mut(#id_name)?; }
+                    }
                 })
                 .collect::<Vec<_>>();
             get_mut.push(quote! {
@@ -429,16 +604,22 @@ This struct is a version `{struct_name}` that each field in its own [Storage](::
             let mut get_many_mut = Vec::new();
             if fields.is_empty() {
                 // No fields
-                iter_mut.push(quote! { ::std::iter::empty() });
-                get_many_mut.push(quote! { ::std::iter::empty() });
+                iter_mut.push(quote! { ::core::iter::empty() });
+                get_many_mut.push(quote! { ::core::iter::empty() });
             } else {
                 // Collect fields
                 iter_mut = struct_fields
                     .iter()
                     .map(|field| {
                         let name = &field.name;
-                        quote! {
-                            let #name = unsafe { self.#name.get_many_unchecked_mut(self.ids.ids()) };
+                        if field.is_optional() {
+                            quote! {
+                                let #name = unsafe { self.#name.get_many_optional_unchecked_mut(self.ids.ids()) };
+                            }
+                        } else {
+                            quote! {
+                                let #name = unsafe { self.#name.get_many_unchecked_mut(self.ids.ids()) };
+                            }
                         }
                     })
                     .collect();
@@ -453,7 +634,7 @@ This struct is a version `{struct_name}` that each field in its own [Storage](::
                 iter_mut.extend(zip);
 
                 // Construct the arguments for the lambda function
-                let mut args = quote! { id };
+                let mut args = quote! { #id_name };
                 for field in struct_fields.iter().map(|field| &field.name) {
                     args = quote! { (#args, #field) };
                 }
@@ -462,7 +643,7 @@ This struct is a version `{struct_name}` that each field in its own [Storage](::
                 iter_mut.push(quote! {
                     .filter_map(|#args| {
                         Some((
-                            id,
+                            #id_name,
                             #struct_ref_mut_name {
                                 #(#fields)*
                             }
@@ -477,8 +658,14 @@ This struct is a version `{struct_name}` that each field in its own [Storage](::
                 // Collect fields
                 let get_fields = struct_fields.iter().map(|field| {
                     let name = &field.name;
-                    quote! {
-                        let #name = unsafe { self.#name.get_many_unchecked_mut(#ids_expr.clone()) };
+                    if field.is_optional() {
+                        quote! {
+                            let #name = unsafe { self.#name.get_many_optional_unchecked_mut(#ids_expr.clone()) };
+                        }
+                    } else {
+                        quote! {
+                            let #name = unsafe { self.#name.get_many_unchecked_mut(#ids_expr.clone()) };
+                        }
                     }
                 });
                 get_many_mut.extend(get_fields);
@@ -512,6 +699,57 @@ This struct is a version `{struct_name}` that each field in its own [Storage](::
                 });
             }
 
+            let get_many_optional_mut = {
+                let ids_expr = quote! { __ids };
+                let present_name = quote! { __present }; // NOTE: mangled name to avoid conflicts
+
+                // Collect fields
+                let mut get_many = vec![quote! {
+                    let #present_name = unsafe { self.ids.get_many_optional_unchecked_mut(#ids_expr.clone()) };
+                }];
+                get_many.extend(struct_fields.iter().map(|field| {
+                    let name = &field.name;
+                    quote! {
+                        let #name = unsafe { self.#name.get_many_optional_unchecked_mut(#ids_expr.clone()) };
+                    }
+                }));
+
+                // Zip fields
+                get_many.push(quote! { #present_name });
+                get_many.extend(struct_fields.iter().map(|field| {
+                    let name = &field.name;
+                    quote! { .zip(#name) }
+                }));
+
+                // Construct the arguments for the lambda function
+                let mut args = quote! { #present_name };
+                for field in struct_fields.iter().map(|field| &field.name) {
+                    args = quote! { (#args, #field) };
+                }
+
+                // Filter absent components, except for the optional ones
+                let filtered = struct_fields.iter().map(|field| {
+                    let name = &field.name;
+                    if field.is_optional() {
+                        quote! {}
+                    } else {
+                        quote! { let #name = #name?; }
+                    }
+                });
+
+                // Construct the lambda function
+                get_many.push(quote! {
+                    .map(|#args| {
+                        #present_name?;
+                        #(#filtered)*
+                        Some(#struct_ref_mut_name {
+                            #(#fields)*
+                        })
+                    })
+                });
+                get_many
+            };
+
             let get_doc = format!(
                 r#"Get an immutable reference to all components of this archetype, i.e. a [`{struct_ref_name}`]"#
             );
@@ -525,6 +763,18 @@ This struct is a version `{struct_name}` that each field in its own [Storage](::
                 r#"Iterate mutably over all components of this archetype, i.e. over [`{struct_ref_mut_name}`]"#
             );
 
+            let sort_by_doc = format!(
+                r#"Sort the entities with a comparator function over [`{struct_ref_name}`], returning the map from old to new id's.
+
+The sort is stable. Afterwards, the entities are iterated in the sorted order
+for families that keep the insertion order (e.g. `Vec`, `Arena` or `BTreeStorage`)."#
+            );
+            let sort_by_key_doc = format!(
+                r#"Sort the entities with a key extraction function over [`{struct_ref_name}`], returning the map from old to new id's.
+
+See [`{struct_of_name}::sort_by`] for more details."#
+            );
+
             let get_many_unchecked_mut_doc = format!(
                 r#"**NOTE**: This function is used internally by the proc macros, you should not call it manually.
 
@@ -534,25 +784,36 @@ Get mutable references to all id's in the iterator, returning an iterator of [`{
 The given `ids` must not repeat and must be valid and present id's in the storage."#
             );
 
+            let get_many_optional_unchecked_mut_doc = format!(
+                r#"**NOTE**: This function is used internally by the proc macros, you should not call it manually.
+
+Get mutable references to all id's in the iterator, returning an iterator of [`{struct_ref_mut_name}`], or `None` for absent entities.
+
+# Safety
+The given `ids` must not repeat and must be valid id's in the storage."#
+            );
+
             quote! {
                 impl<#generics_family> #struct_of_name<#generics_family_use> {
                     pub fn new(&self) -> Self {
                         Self::default()
                     }
 
-                    pub fn phantom_data(&self) -> ::std::marker::PhantomData<#generic_family_name> {
-                        ::std::default::Default::default()
+                    pub fn phantom_data(&self) -> ::core::marker::PhantomData<#generic_family_name> {
+                        ::core::default::Default::default()
                     }
 
                     #[doc = #get_doc]
                     pub fn get(&self, id: #generic_family_name::Id) -> Option<#struct_ref_name<'_, #generics_use>> {
                         use ::stecs::storage::Storage;
+                        let #id_name = id;
                         #(#get)*
                     }
 
                     #[doc = #get_mut_doc]
                     pub fn get_mut(&mut self, id: #generic_family_name::Id) -> Option<#struct_ref_mut_name<'_, #generics_use>> {
                         use ::stecs::storage::Storage;
+                        let #id_name = id;
                         #(#get_mut)*
                     }
 
@@ -568,6 +829,38 @@ The given `ids` must not repeat and must be valid and present id's in the storag
                         #(#iter_mut)*
                     }
 
+                    #to_vec
+
+                    #[doc = #sort_by_doc]
+                    pub fn sort_by(
+                        &mut self,
+                        mut compare: impl FnMut(&#struct_ref_name<'_, #generics_use>, &#struct_ref_name<'_, #generics_use>) -> ::core::cmp::Ordering,
+                    ) -> ::stecs::hashbrown::HashMap<#generic_family_name::Id, #generic_family_name::Id>
+                    where
+                        #generic_family_name::Id: ::core::cmp::Eq + ::core::hash::Hash,
+                    {
+                        use ::stecs::archetype::Archetype;
+                        let mut order: ::stecs::__private::Vec<_> = self.ids().collect();
+                        order.sort_by(|&a, &b| {
+                            let a = self.get(a).expect("invalid id: entry absent");
+                            let b = self.get(b).expect("invalid id: entry absent");
+                            compare(&a, &b)
+                        });
+                        let new_ids = self.reorder(&order);
+                        order.into_iter().zip(new_ids).collect()
+                    }
+
+                    #[doc = #sort_by_key_doc]
+                    pub fn sort_by_key<K: ::core::cmp::Ord>(
+                        &mut self,
+                        mut f: impl FnMut(&#struct_ref_name<'_, #generics_use>) -> K,
+                    ) -> ::stecs::hashbrown::HashMap<#generic_family_name::Id, #generic_family_name::Id>
+                    where
+                        #generic_family_name::Id: ::core::cmp::Eq + ::core::hash::Hash,
+                    {
+                        self.sort_by(|a, b| f(a).cmp(&f(b)))
+                    }
+
                     #[doc = #get_many_unchecked_mut_doc]
                     pub unsafe fn get_many_unchecked_mut<#lifetime_ref_name>(
                         &#lifetime_ref_name mut self,
@@ -575,6 +868,34 @@ The given `ids` must not repeat and must be valid and present id's in the storag
                     ) -> impl Iterator<Item = #struct_ref_mut_name<#lifetime_ref_name, #generics_use>> {
                         #(#get_many_mut)*
                     }
+
+                    #[doc = #get_many_optional_unchecked_mut_doc]
+                    pub unsafe fn get_many_optional_unchecked_mut<#lifetime_ref_name>(
+                        &#lifetime_ref_name mut self,
+                        __ids: impl Iterator<Item = #generic_family_name::Id> + Clone,
+                    ) -> impl Iterator<Item = Option<#struct_ref_mut_name<#lifetime_ref_name, #generics_use>>> {
+                        use ::stecs::storage::Storage;
+                        #(#get_many_optional_mut)*
+                    }
+
+                    // NOTE: queries access the nested archetypes the same way as the storages
+                    #[doc(hidden)]
+                    pub fn query_get(&self, id: #generic_family_name::Id) -> Option<#struct_ref_name<'_, #generics_use>> {
+                        self.get(id)
+                    }
+
+                    #[doc(hidden)]
+                    pub fn query_get_mut(&mut self, id: #generic_family_name::Id) -> Option<#struct_ref_mut_name<'_, #generics_use>> {
+                        self.get_mut(id)
+                    }
+
+                    #[doc(hidden)]
+                    pub unsafe fn query_get_many_unchecked_mut<#lifetime_ref_name>(
+                        &#lifetime_ref_name mut self,
+                        __ids: impl Iterator<Item = #generic_family_name::Id> + Clone,
+                    ) -> impl Iterator<Item = #struct_ref_mut_name<#lifetime_ref_name, #generics_use>> {
+                        unsafe { self.get_many_unchecked_mut(__ids) }
+                    }
                 }
 
                 impl<#generics_family> IntoIterator for #struct_of_name<#generics_family_use> {
@@ -585,44 +906,336 @@ The given `ids` must not repeat and must be valid and present id's in the storag
                         ::stecs::archetype::ArchetypeIntoIter::new(self)
                     }
                 }
+
+                impl<#generics_family> FromIterator<#struct_name<#generics_use>> for #struct_of_name<#generics_family_use> {
+                    fn from_iter<__I: IntoIterator<Item = #struct_name<#generics_use>>>(iter: __I) -> Self {
+                        let mut archetype = Self::default();
+                        for value in iter {
+                            ::stecs::archetype::Archetype::insert(&mut archetype, value);
+                        }
+                        archetype
+                    }
+                }
+
+                impl<#generics_family> From<::stecs::__private::Vec<#struct_name<#generics_use>>> for #struct_of_name<#generics_family_use> {
+                    fn from(values: ::stecs::__private::Vec<#struct_name<#generics_use>>) -> Self {
+                        values.into_iter().collect()
+                    }
+                }
+
+                impl<#generics_family> From<#struct_of_name<#generics_family_use>> for ::stecs::__private::Vec<#struct_name<#generics_use>> {
+                    fn from(archetype: #struct_of_name<#generics_family_use>) -> Self {
+                        archetype.into_iter().map(|(_, value)| value).collect()
+                    }
+                }
             }
         };
 
         let struct_of_archetype = {
-            let mut insert = struct_fields
+            // NOTE: the hooks see the entity while it is in the archetype
+            let on_insert = match &struct_on_insert {
+                Some(path) => quote! {
+                    #path(#id_name, self.get(#id_name).expect("the entity has just been inserted"));
+                },
+                None => quote! {},
+            };
+            let on_remove = match &struct_on_remove {
+                Some(path) => quote! {
+                    if let Some(__item) = self.get(#id_name) {
+                        #path(#id_name, __item);
+                    }
+                },
+                None => quote! {},
+            };
+
+            let insert_fields = struct_fields
                 .iter()
                 .map(|field| {
                     let name = &field.name;
+                    if field.optional.is_some() {
+                        quote! {
+                            match value.#name {
+                                Some(value) => {
+                                    ::stecs::archetype::Archetype::insert(&mut self.#name, value);
+                                }
+                                None => {
+                                    ::stecs::archetype::OptionalArchetype::insert_none(&mut self.#name);
+                                }
+                            }
+                        }
+                    } else if field.presence.is_some() {
+                        quote! {
+                            match value.#name {
+                                Some(value) => {
+                                    self.#name.insert(value);
+                                }
+                                None => {
+                                    self.#name.insert_none();
+                                }
+                            }
+                        }
+                    } else {
+                        quote! {
+                            self.#name.insert(value.#name);
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mut insert = insert_fields.clone();
+            insert.push(quote! { let #id_name = self.ids.insert(()); });
+            insert.push(on_insert.clone());
+            insert.push(quote! { #id_name });
+
+            // NOTE: the id is checked on the `ids` storage first,
+            // then the other storages of the same family must accept it as well
+            let mut insert_with_id = vec![quote! {
+                if self.ids.insert_with_id(#id_name, ()).is_err() {
+                    return Err(value);
+                }
+            }];
+            insert_with_id.extend(struct_fields.iter().map(|field| {
+                let name = &field.name;
+                let insert = if field.optional.is_some() {
+                    quote! {
+                        match value.#name {
+                            Some(value) => {
+                                ::stecs::archetype::Archetype::insert_with_id(&mut self.#name, #id_name, value).is_ok()
+                            }
+                            None => {
+                                ::stecs::archetype::OptionalArchetype::insert_none_with_id(&mut self.#name, #id_name)
+                            }
+                        }
+                    }
+                } else if field.presence.is_some() {
+                    quote! {
+                        match value.#name {
+                            Some(value) => self.#name.insert_with_id(#id_name, value).is_ok(),
+                            None => self.#name.insert_none_with_id(#id_name),
+                        }
+                    }
+                } else if field.nested {
                     quote! {
-                        self.#name.insert(value.#name);
+                        ::stecs::archetype::Archetype::insert_with_id(&mut self.#name, #id_name, value.#name).is_ok()
+                    }
+                } else {
+                    quote! { self.#name.insert_with_id(#id_name, value.#name).is_ok() }
+                };
+                quote! {
+                    assert!(#insert, "storages of the same family must accept the same id's");
+                }
+            }));
+            insert_with_id.push(on_insert.clone());
+            insert_with_id.push(quote! { Ok(()) });
+
+            // NOTE: the `ids` storage is in sync with the other storages of the same family,
+            // so once it accepts the entity, the rest of them accept the components as well
+            // and nothing has to be rolled back (which would desync e.g. the id counters)
+            let mut try_insert = vec![quote! {
+                let #id_name = match self.ids.try_insert(()) {
+                    Ok(id) => id,
+                    Err(err) => return Err(err.map(|()| value)),
+                };
+            }];
+            try_insert.extend(insert_fields.iter().cloned());
+            try_insert.push(on_insert.clone());
+            try_insert.push(quote! { Ok(#id_name) });
+
+            let mut remove = vec![on_remove.clone()];
+            remove.extend(struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    if field.optional.is_some() {
+                        quote! {
+                            let #name = ::stecs::archetype::OptionalArchetype::take(&mut self.#name, #id_name)?;
+                        }
+                    } else if field.presence.is_some() {
+                        quote! {
+                            let #name = self.#name.take(#id_name)?;
+                        }
+                    } else {
+                        quote! {
+                            let #name = self.#name.remove(#id_name)?;
+                        }
                     }
+                }));
+            remove.push(quote! { self.ids.remove(#id_name)?; });
+            let fields = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    quote! {#name}
                 })
                 .collect::<Vec<_>>();
-            insert.push(quote! { let id = self.ids.insert(()); });
-            insert.push(quote! { id });
+            remove.push(quote! { Some( #struct_name { #(#fields),* } )});
 
-            let mut remove = struct_fields
+            // NOTE: the components are replaced in place, so the id's and the order are kept
+            let replace = struct_fields
                 .iter()
                 .map(|field| {
                     let name = &field.name;
+                    let replace = if field.optional.is_some() {
+                        quote! { ::stecs::archetype::OptionalArchetype::replace_optional(&mut self.#name, #id_name, value.#name).ok() }
+                    } else if field.presence.is_some() {
+                        quote! { self.#name.replace(#id_name, value.#name) }
+                    } else if field.tag {
+                        quote! { self.#name.set(#id_name, value.#name) }
+                    } else if field.nested {
+                        quote! { ::stecs::archetype::Archetype::replace(&mut self.#name, #id_name, value.#name).ok() }
+                    } else {
+                        quote! { self.#name.get_mut(#id_name).map(|component| ::core::mem::replace(component, value.#name)) }
+                    };
                     quote! {
-                        let #name = self.#name.remove(id)?;
+                        #name: #replace.expect("storages of the same family must contain the same id's"),
                     }
                 })
                 .collect::<Vec<_>>();
-            remove.push(quote! { self.ids.remove(id)?; });
-            let fields = struct_fields
+
+            let drain_name = syn::Ident::new(
+                &format!("{struct_name}Drain"),
+                proc_macro2::Span::call_site(),
+            );
+            let drain_fields = struct_fields
                 .iter()
                 .map(|field| {
                     let name = &field.name;
-                    quote! {#name}
+                    let ty = &field.ty;
+                    let ty = if let Some(ty) = &field.optional {
+                        quote! { <<#ty as ::stecs::archetype::SplitFields<#option_family_name>>::StructOf as ::stecs::archetype::OptionalArchetype<#generic_family_name>>::DrainOptional }
+                    } else if let Some(ty) = &field.presence {
+                        quote! { ::stecs::storage::bitset::BitsetDrain<#generic_family_name, #ty> }
+                    } else if field.tag {
+                        quote! { ::stecs::storage::bitset::TagDrain }
+                    } else if field.nested {
+                        quote! { <<#ty as ::stecs::archetype::SplitFields<#generic_family_name>>::StructOf as ::stecs::archetype::Archetype<#generic_family_name>>::Drain }
+                    } else {
+                        quote! { <#generic_family_name::Storage<#ty> as ::stecs::storage::Storage<#ty>>::Drain }
+                    };
+                    quote! { #name: #ty, }
+                })
+                .collect::<Vec<_>>();
+            let drain = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    if field.optional.is_some() {
+                        quote! { #name: ::stecs::archetype::OptionalArchetype::drain_optional(&mut self.#name), }
+                    } else if field.presence.is_some() {
+                        quote! { #name: self.#name.drain_optional(), }
+                    } else if field.nested {
+                        quote! { #name: ::stecs::archetype::Archetype::drain(&mut self.#name), }
+                    } else {
+                        quote! { #name: self.#name.drain(), }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let into_drain = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    if field.optional.is_some() {
+                        quote! { #name: ::stecs::archetype::OptionalArchetype::drain_optional(&mut self.#name), }
+                    } else if field.presence.is_some() {
+                        quote! { #name: self.#name.drain_optional(), }
+                    } else if field.nested {
+                        quote! { #name: ::stecs::archetype::Archetype::into_drain(::core::mem::take(&mut self.#name)), }
+                    } else {
+                        quote! { #name: self.#name.drain(), }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mut drain_take = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    let ty = &field.ty;
+                    if let Some(ty) = &field.optional {
+                        quote! { let #name = <<#ty as ::stecs::archetype::SplitFields<#option_family_name>>::StructOf as ::stecs::archetype::OptionalArchetype<#generic_family_name>>::drain_optional_take(&mut self.#name, #id_name)?; }
+                    } else if field.presence.is_some() || field.tag {
+                        // NOTE: bitsets are only stored in dense families, which drain in the same order
+                        quote! { let (_, #name) = self.#name.next()?; }
+                    } else if field.nested {
+                        quote! { let #name = <<#ty as ::stecs::archetype::SplitFields<#generic_family_name>>::StructOf as ::stecs::archetype::Archetype<#generic_family_name>>::drain_take(&mut self.#name, #id_name)?; }
+                    } else {
+                        quote! { let #name = <#generic_family_name::Storage<#ty> as ::stecs::storage::Storage<#ty>>::drain_take(&mut self.#name, #id_name)?; }
+                    }
+                })
+                .collect::<Vec<_>>();
+            drain_take.push(quote! { Some(#struct_name { #(#fields),* }) });
+            let drain_doc = format!(
+                r#"Generated by `#[derive(SplitFields)]`.
+An owning iterator over the entities of [`{struct_of_name}`], created by [`Archetype::drain`](::stecs::archetype::Archetype::drain)."#
+            );
+
+            // NOTE: the entities are effectively removed and reinserted, so the hooks are called for them
+            let drain_hooks = if struct_on_remove.is_some() {
+                quote! {
+                    for #id_name in self.ids.ids() {
+                        #on_remove
+                    }
+                }
+            } else {
+                quote! {}
+            };
+            let reorder_remove_hooks = if struct_on_remove.is_some() {
+                quote! {
+                    for &#id_name in order {
+                        #on_remove
+                    }
+                }
+            } else {
+                quote! {}
+            };
+            let reorder_insert_hooks = if struct_on_insert.is_some() {
+                quote! {
+                    for &#id_name in &__new_ids {
+                        #on_insert
+                    }
+                }
+            } else {
+                quote! {}
+            };
+            let reorder = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    if field.nested {
+                        quote! { ::stecs::archetype::Archetype::reorder(&mut self.#name, order); }
+                    } else {
+                        quote! { self.#name.reorder(order); }
+                    }
                 })
                 .collect::<Vec<_>>();
-            remove.push(quote! { Some( #struct_name { #(#fields),* } )});
 
             quote! {
+                #[doc = #drain_doc]
+                #vis struct #drain_name<#generics_family> {
+                    ids: <#generic_family_name::Storage<()> as ::stecs::storage::Storage<()>>::Drain,
+                    #(#drain_fields)*
+                }
+
+                impl<#generics_family> #drain_name<#generics_family_use> {
+                    /// Take the components of the entity with a given id out of the columns.
+                    fn take(&mut self, #id_name: #generic_family_name::Id) -> Option<#struct_name<#generics_use>> {
+                        #(#drain_take)*
+                    }
+                }
+
+                impl<#generics_family> Iterator for #drain_name<#generics_family_use> {
+                    type Item = (#generic_family_name::Id, #struct_name<#generics_use>);
+                    fn next(&mut self) -> Option<Self::Item> {
+                        // NOTE: the other columns follow the id's of the `ids` column
+                        let (#id_name, ()) = self.ids.next()?;
+                        self.take(#id_name).map(|value| (#id_name, value))
+                    }
+                    fn size_hint(&self) -> (usize, Option<usize>) {
+                        self.ids.size_hint()
+                    }
+                }
+
                 impl<#generics_family> ::stecs::archetype::Archetype<#generic_family_name> for #struct_of_name<#generics_family_use> {
                     type Item = #struct_name<#generics_use>;
+                    type Drain = #drain_name<#generics_family_use>;
                     fn ids(&self) -> impl Iterator<Item = #generic_family_name::Id> {
                         use ::stecs::storage::Storage;
                         self.ids.ids()
@@ -631,12 +1244,935 @@ The given `ids` must not repeat and must be valid and present id's in the storag
                         use ::stecs::storage::Storage;
                         #(#insert)*
                     }
+                    fn try_insert(&mut self, value: Self::Item) -> Result<#generic_family_name::Id, ::stecs::storage::InsertError<Self::Item>> {
+                        use ::stecs::storage::Storage;
+                        #(#try_insert)*
+                    }
+                    fn insert_with_id(&mut self, id: #generic_family_name::Id, value: Self::Item) -> Result<(), Self::Item> {
+                        use ::stecs::storage::Storage;
+                        let #id_name = id;
+                        #(#insert_with_id)*
+                    }
                     fn remove(&mut self, id: #generic_family_name::Id) -> Option<Self::Item> {
                         use ::stecs::storage::Storage;
+                        let #id_name = id;
                         #(#remove)*
                     }
+                    fn replace(&mut self, id: #generic_family_name::Id, value: Self::Item) -> Result<Self::Item, Self::Item> {
+                        use ::stecs::storage::Storage;
+                        let #id_name = id;
+                        if self.ids.get(#id_name).is_none() {
+                            return Err(value);
+                        }
+                        #on_remove
+                        let __old = #struct_name { #(#replace)* };
+                        #on_insert
+                        Ok(__old)
+                    }
+                    fn drain(&mut self) -> Self::Drain {
+                        use ::stecs::storage::Storage;
+                        #drain_hooks
+                        #drain_name {
+                            ids: self.ids.drain(),
+                            #(#drain)*
+                        }
+                    }
+                    fn drain_take(drain: &mut Self::Drain, id: #generic_family_name::Id) -> Option<Self::Item> {
+                        <#generic_family_name::Storage<()> as ::stecs::storage::Storage<()>>::drain_take(&mut drain.ids, id)?;
+                        drain.take(id)
+                    }
+                    fn into_drain(mut self) -> Self::Drain {
+                        use ::stecs::storage::Storage;
+                        #drain_name {
+                            ids: self.ids.drain(),
+                            #(#into_drain)*
+                        }
+                    }
+                    fn reorder(&mut self, order: &[#generic_family_name::Id]) -> ::stecs::__private::Vec<#generic_family_name::Id> {
+                        use ::stecs::storage::Storage;
+                        #reorder_remove_hooks
+                        #(#reorder)*
+                        let __new_ids = self.ids.reorder(order);
+                        #reorder_insert_hooks
+                        __new_ids
+                    }
+                }
+            }
+        };
+
+        // NOTE: bitset storages cannot be used in an `OptionFamily`,
+        // so structs with `#[split(optional)]` fields cannot be nested optionally
+        let struct_of_optional = if is_dense {
+            quote! {}
+        } else {
+            let generics_option_use = {
+                let params = struct_generics.params.iter().map(|param| match param {
+                    syn::GenericParam::Type(param) => {
+                        let ident = &param.ident;
+                        quote! { #ident }
+                    }
+                    syn::GenericParam::Lifetime(param) => {
+                        let ident = &param.lifetime;
+                        quote! { #ident }
+                    }
+                    syn::GenericParam::Const(param) => {
+                        let ident = &param.ident;
+                        quote! { #ident }
+                    }
+                });
+                let mut params: Vec<_> = params.collect();
+                let i = struct_generics
+                    .params
+                    .iter()
+                    .position(|param| !matches!(param, syn::GenericParam::Lifetime(_)))
+                    .unwrap_or(params.len());
+                params.insert(i, option_family_name.clone());
+                quote! { #(#params),* }
+            };
+
+            // Nested archetypes must also support absent entities
+            let constraints = struct_fields
+                .iter()
+                .filter(|field| field.nested && field.optional.is_none())
+                .map(|field| {
+                    let ty = &field.ty;
+                    quote! {
+                        <#ty as ::stecs::archetype::SplitFields<#option_family_name>>::StructOf:
+                            ::stecs::archetype::OptionalArchetype<#generic_family_name, Item = #ty>
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let insert_none_fields = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    if field.nested {
+                        // NOTE: an optional nested archetype is doubly optional,
+                        // so inserting it as present but empty is equivalent to being absent
+                        quote! { ::stecs::archetype::OptionalArchetype::insert_none(&mut self.#name); }
+                    } else {
+                        quote! { self.#name.insert_none(); }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mut insert_none = insert_none_fields.clone();
+            insert_none.push(quote! { self.ids.insert_none() });
+
+            let mut insert_none_with_id = vec![quote! {
+                if !self.ids.insert_none_with_id(#id_name) {
+                    return false;
+                }
+            }];
+            insert_none_with_id.extend(struct_fields.iter().map(|field| {
+                let name = &field.name;
+                let insert = if field.nested {
+                    quote! { ::stecs::archetype::OptionalArchetype::insert_none_with_id(&mut self.#name, #id_name) }
+                } else {
+                    quote! { self.#name.insert_none_with_id(#id_name) }
+                };
+                quote! {
+                    assert!(#insert, "storages of the same family must accept the same id's");
+                }
+            }));
+            insert_none_with_id.push(quote! { true });
+
+            // NOTE: same as in `try_insert`, the other storages accept the id once `ids` does
+            let mut try_insert_none = vec![quote! {
+                let #id_name = self.ids.try_insert_none()?;
+            }];
+            try_insert_none.extend(insert_none_fields.iter().cloned());
+            try_insert_none.push(quote! { Ok(#id_name) });
+
+            let mut take = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    if field.nested {
+                        quote! { let #name = ::stecs::archetype::OptionalArchetype::take(&mut self.#name, #id_name); }
+                    } else {
+                        quote! { let #name = self.#name.take(#id_name); }
+                    }
+                })
+                .collect::<Vec<_>>();
+            take.push(quote! { let __present = self.ids.take(#id_name)?; });
+            let fields = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    if field.optional.is_some() {
+                        quote! { #name: #name.flatten() }
+                    } else {
+                        quote! { #name: #name.flatten()? }
+                    }
+                })
+                .collect::<Vec<_>>();
+            take.push(quote! {
+                match __present {
+                    None => Some(None),
+                    Some(()) => Some(Some(#struct_name { #(#fields),* })),
+                }
+            });
+
+            // NOTE: the value is split into the optional components first,
+            // so that the absent entity replaces all of them with `None`
+            let replace_locals = (0..struct_fields.len())
+                .map(|i| syn::Ident::new(&format!("__field{i}"), proc_macro2::Span::call_site()))
+                .collect::<Vec<_>>();
+            let replace_split = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    quote! { Some(value.#name), }
+                })
+                .collect::<Vec<_>>();
+            let replace_none = struct_fields
+                .iter()
+                .map(|_| quote! { None, })
+                .collect::<Vec<_>>();
+            let replace_optional = struct_fields
+                .iter()
+                .zip(&replace_locals)
+                .map(|(field, local)| {
+                    let name = &field.name;
+                    let replace = if field.optional.is_some() {
+                        quote! { ::stecs::archetype::OptionalArchetype::replace_optional(&mut self.#name, #id_name, #local.flatten()).ok() }
+                    } else if field.nested {
+                        quote! { ::stecs::archetype::OptionalArchetype::replace_optional(&mut self.#name, #id_name, #local).ok() }
+                    } else {
+                        quote! { self.#name.replace(#id_name, #local) }
+                    };
+                    quote! {
+                        let #name = #replace.expect("storages of the same family must contain the same id's");
+                    }
+                })
+                .collect::<Vec<_>>();
+            let replace_fields = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    if field.optional.is_some() {
+                        quote! { #name }
+                    } else {
+                        quote! { #name: #name.expect("the entity was present") }
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            let drain_optional_name = syn::Ident::new(
+                &format!("{struct_name}DrainOptional"),
+                proc_macro2::Span::call_site(),
+            );
+            let drain_optional_fields = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    let ty = &field.ty;
+                    let ty = if let Some(ty) = &field.optional {
+                        quote! { <<#ty as ::stecs::archetype::SplitFields<::stecs::storage::option::OptionFamily<#option_family_name>>>::StructOf as ::stecs::archetype::OptionalArchetype<#option_family_name>>::DrainOptional }
+                    } else if field.nested {
+                        quote! { <<#ty as ::stecs::archetype::SplitFields<#option_family_name>>::StructOf as ::stecs::archetype::OptionalArchetype<#generic_family_name>>::DrainOptional }
+                    } else {
+                        quote! { <#generic_family_name::Storage<Option<#ty>> as ::stecs::storage::Storage<Option<#ty>>>::Drain }
+                    };
+                    quote! { #name: #ty, }
+                })
+                .collect::<Vec<_>>();
+            let drain_optional = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    if field.nested {
+                        quote! { #name: ::stecs::archetype::OptionalArchetype::drain_optional(&mut self.#name), }
+                    } else {
+                        quote! { #name: self.#name.drain_optional(), }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let mut drain_optional_take = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    let ty = &field.ty;
+                    if let Some(ty) = &field.optional {
+                        quote! { let #name = <<#ty as ::stecs::archetype::SplitFields<::stecs::storage::option::OptionFamily<#option_family_name>>>::StructOf as ::stecs::archetype::OptionalArchetype<#option_family_name>>::drain_optional_take(&mut self.#name, #id_name)?; }
+                    } else if field.nested {
+                        quote! { let #name = <<#ty as ::stecs::archetype::SplitFields<#option_family_name>>::StructOf as ::stecs::archetype::OptionalArchetype<#generic_family_name>>::drain_optional_take(&mut self.#name, #id_name)?; }
+                    } else {
+                        quote! { let #name = <#generic_family_name::Storage<Option<#ty>> as ::stecs::storage::Storage<Option<#ty>>>::drain_take(&mut self.#name, #id_name)?; }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let fields = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    if field.optional.is_some() {
+                        quote! { #name }
+                    } else {
+                        quote! { #name: #name? }
+                    }
+                })
+                .collect::<Vec<_>>();
+            drain_optional_take.push(quote! {
+                match __present {
+                    None => Some(None),
+                    Some(()) => Some(Some(#struct_name { #(#fields),* })),
+                }
+            });
+            let drain_optional_doc = format!(
+                r#"Generated by `#[derive(SplitFields)]`.
+An owning iterator over the present and absent entities of [`{struct_of_name}`] in an [`OptionFamily`](::stecs::storage::option::OptionFamily),
+created by [`OptionalArchetype::drain_optional`](::stecs::archetype::OptionalArchetype::drain_optional)."#
+            );
+
+            quote! {
+                #[doc = #drain_optional_doc]
+                #vis struct #drain_optional_name<#generics_family>
+                where
+                    #(#constraints),*
+                {
+                    ids: <#generic_family_name::Storage<Option<()>> as ::stecs::storage::Storage<Option<()>>>::Drain,
+                    #(#drain_optional_fields)*
+                }
+
+                impl<#generics_family> #drain_optional_name<#generics_family_use>
+                where
+                    #(#constraints),*
+                {
+                    /// Take the components of the entity with a given id out of the columns.
+                    fn take(&mut self, #id_name: #generic_family_name::Id, __present: Option<()>) -> Option<Option<#struct_name<#generics_use>>> {
+                        #(#drain_optional_take)*
+                    }
+                }
+
+                impl<#generics_family> Iterator for #drain_optional_name<#generics_family_use>
+                where
+                    #(#constraints),*
+                {
+                    type Item = (#generic_family_name::Id, Option<#struct_name<#generics_use>>);
+                    fn next(&mut self) -> Option<Self::Item> {
+                        // NOTE: the other columns follow the id's of the `ids` column
+                        let (#id_name, __present) = self.ids.next()?;
+                        self.take(#id_name, __present).map(|value| (#id_name, value))
+                    }
+                    fn size_hint(&self) -> (usize, Option<usize>) {
+                        self.ids.size_hint()
+                    }
+                }
+
+                impl<#generics_family> ::stecs::archetype::OptionalArchetype<#generic_family_name> for #struct_of_name<#generics_option_use>
+                where
+                    #(#constraints),*
+                {
+                    type DrainOptional = #drain_optional_name<#generics_family_use>;
+                    fn drain_optional_take(drain: &mut Self::DrainOptional, id: #generic_family_name::Id) -> Option<Option<Self::Item>> {
+                        let __present = <#generic_family_name::Storage<Option<()>> as ::stecs::storage::Storage<Option<()>>>::drain_take(&mut drain.ids, id)?;
+                        drain.take(id, __present)
+                    }
+                    fn drain_optional(&mut self) -> Self::DrainOptional {
+                        #drain_optional_name {
+                            ids: self.ids.drain_optional(),
+                            #(#drain_optional)*
+                        }
+                    }
+                    fn insert_none(&mut self) -> #generic_family_name::Id {
+                        #(#insert_none)*
+                    }
+                    fn try_insert_none(&mut self) -> Result<#generic_family_name::Id, ::stecs::storage::InsertError<()>> {
+                        #(#try_insert_none)*
+                    }
+                    fn insert_none_with_id(&mut self, id: #generic_family_name::Id) -> bool {
+                        let #id_name = id;
+                        #(#insert_none_with_id)*
+                    }
+                    fn take(&mut self, id: #generic_family_name::Id) -> Option<Option<Self::Item>> {
+                        let #id_name = id;
+                        #(#take)*
+                    }
+                    fn replace_optional(&mut self, id: #generic_family_name::Id, value: Option<Self::Item>) -> Result<Option<Self::Item>, Option<Self::Item>> {
+                        let #id_name = id;
+                        let Some(__present) = self.ids.replace(#id_name, value.as_ref().map(|_| ())) else {
+                            return Err(value);
+                        };
+                        let (#(#replace_locals,)*) = match value {
+                            Some(value) => (#(#replace_split)*),
+                            None => (#(#replace_none)*),
+                        };
+                        #(#replace_optional)*
+                        Ok(match __present {
+                            None => None,
+                            Some(()) => Some(#struct_name { #(#replace_fields),* }),
+                        })
+                    }
+                }
+            }
+        };
+
+        // NOTE: `StructRef` replaces the lifetimes of the struct, so they would not match the inherent methods
+        let has_lifetimes = struct_generics
+            .params
+            .iter()
+            .any(|param| matches!(param, syn::GenericParam::Lifetime(_)));
+        let struct_of_archetype_ref = if has_lifetimes {
+            quote! {}
+        } else {
+            quote! {
+                impl<#generics_family> ::stecs::archetype::ArchetypeRef<#generic_family_name> for #struct_of_name<#generics_family_use> {
+                    fn get(&self, id: #generic_family_name::Id) -> Option<#struct_ref_name<'_, #generics_use>> {
+                        Self::get(self, id)
+                    }
+                    fn get_mut(&mut self, id: #generic_family_name::Id) -> Option<#struct_ref_mut_name<'_, #generics_use>> {
+                        Self::get_mut(self, id)
+                    }
+                }
+            }
+        };
+
+        let struct_of_has_field = struct_fields
+            .iter()
+            .map(|field| {
+                let name = &field.name;
+                let field_name = crate::field::field_name_type(name);
+                let ty = &field.ty;
+                let storage = if let Some(ty) = &field.optional {
+                    quote! { <#ty as ::stecs::archetype::SplitFields<#option_family_name>>::StructOf }
+                } else if let Some(ty) = &field.presence {
+                    quote! { ::stecs::storage::bitset::BitsetStorage<#generic_family_name, #ty> }
+                } else if field.tag {
+                    quote! { ::stecs::storage::bitset::TagStorage<#generic_family_name> }
+                } else if field.nested {
+                    quote! { <#ty as ::stecs::archetype::SplitFields<#generic_family_name>>::StructOf }
+                } else {
+                    quote! { #generic_family_name::Storage<#ty> }
+                };
+                quote! {
+                    unsafe impl<#generics_family> ::stecs::archetype::HasField<#field_name> for #struct_of_name<#generics_family_use> {
+                        type Storage = #storage;
+                        fn get_field(&self) -> &Self::Storage {
+                            &self.#name
+                        }
+                        fn get_field_mut(&mut self) -> &mut Self::Storage {
+                            &mut self.#name
+                        }
+                        unsafe fn field_ptr(this: *mut Self) -> *mut Self::Storage {
+                            ::core::ptr::addr_of_mut!((*this).#name)
+                        }
+                    }
+                }
+            })
+            .chain(std::iter::once({
+                // NOTE: the id's column is accessible by name too, e.g. for generic queries
+                let field_name = crate::field::field_name_type(&syn::Ident::new("ids", proc_macro2::Span::call_site()));
+                quote! {
+                    unsafe impl<#generics_family> ::stecs::archetype::HasField<#field_name> for #struct_of_name<#generics_family_use> {
+                        type Storage = #generic_family_name::Storage<()>;
+                        fn get_field(&self) -> &Self::Storage {
+                            &self.ids
+                        }
+                        fn get_field_mut(&mut self) -> &mut Self::Storage {
+                            &mut self.ids
+                        }
+                        unsafe fn field_ptr(this: *mut Self) -> *mut Self::Storage {
+                            ::core::ptr::addr_of_mut!((*this).ids)
+                        }
+                    }
+                }
+            }))
+            .collect::<Vec<_>>();
+
+        let struct_of_debug = if struct_debug {
+            let constraints = struct_fields
+                .iter()
+                .map(|field| {
+                    let ty = field
+                        .optional
+                        .as_ref()
+                        .or(field.presence.as_ref())
+                        .unwrap_or(&field.ty);
+                    if field.nested {
+                        quote! { for<#lifetime_ref_name> <#ty as ::stecs::archetype::StructRef>::Ref<#lifetime_ref_name>: ::core::fmt::Debug }
+                    } else {
+                        quote! { #ty: ::core::fmt::Debug }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let header = struct_fields
+                .iter()
+                .map(|field| field.name.unraw().to_string())
+                .collect::<Vec<_>>();
+            let cells = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    quote! { ::stecs::__private::format!("{:?}", __item.#name) }
+                })
+                .collect::<Vec<_>>();
+
+            quote! {
+                impl<#generics_family> ::core::fmt::Debug for #struct_of_name<#generics_family_use>
+                where
+                    #generic_family_name::Id: ::core::fmt::Debug,
+                    #(#constraints),*
+                {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        use ::stecs::storage::Storage;
+                        let rows: ::stecs::__private::Vec<_> = self
+                            .ids
+                            .ids()
+                            .filter_map(|#id_name| {
+                                let __item = self.get(#id_name)?;
+                                Some(::stecs::__private::vec![::stecs::__private::format!("{:?}", #id_name), #(#cells),*])
+                            })
+                            .collect();
+                        ::stecs::__private::debug_table(f, &["id", #(#header),*], &rows)
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        let struct_of_eq = if struct_eq {
+            let constraints = struct_fields
+                .iter()
+                .map(|field| {
+                    let ty = field
+                        .optional
+                        .as_ref()
+                        .or(field.presence.as_ref())
+                        .unwrap_or(&field.ty);
+                    if field.nested {
+                        quote! { for<#lifetime_ref_name> <#ty as ::stecs::archetype::StructRef>::Ref<#lifetime_ref_name>: PartialEq }
+                    } else {
+                        quote! { #ty: PartialEq }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let compare = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    quote! { && __a.#name == __b.#name }
+                })
+                .collect::<Vec<_>>();
+
+            quote! {
+                impl<#generics_family> PartialEq for #struct_of_name<#generics_family_use>
+                where
+                    #(#constraints),*
+                {
+                    fn eq(&self, other: &Self) -> bool {
+                        use ::stecs::storage::Storage;
+                        // NOTE: compare the entities by their id's, regardless of the internal layout
+                        self.ids.ids().count() == other.ids.ids().count()
+                            && self.ids.ids().all(|#id_name| match (self.get(#id_name), other.get(#id_name)) {
+                                (Some(__a), Some(__b)) => true #(#compare)*,
+                                (None, None) => true,
+                                _ => false,
+                            })
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        let struct_of_hash = if struct_hash {
+            let constraints = struct_fields
+                .iter()
+                .map(|field| {
+                    let ty = field
+                        .optional
+                        .as_ref()
+                        .or(field.presence.as_ref())
+                        .unwrap_or(&field.ty);
+                    if field.nested {
+                        quote! { for<#lifetime_ref_name> <#ty as ::stecs::archetype::StructRef>::Ref<#lifetime_ref_name>: ::core::hash::Hash }
+                    } else {
+                        quote! { #ty: ::core::hash::Hash }
+                    }
+                })
+                .collect::<Vec<_>>();
+            let hash = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    quote! { ::core::hash::Hash::hash(&__item.#name, state); }
+                })
+                .collect::<Vec<_>>();
+
+            quote! {
+                impl<#generics_family> ::core::hash::Hash for #struct_of_name<#generics_family_use>
+                where
+                    #generic_family_name::Id: ::core::cmp::Ord + ::core::hash::Hash,
+                    #(#constraints),*
+                {
+                    fn hash<__H: ::core::hash::Hasher>(&self, state: &mut __H) {
+                        use ::stecs::storage::Storage;
+                        // NOTE: hash the entities sorted by their id's, to be consistent with `PartialEq`
+                        let mut ids: ::stecs::__private::Vec<_> = self.ids.ids().collect();
+                        ids.sort_unstable();
+                        ::core::hash::Hash::hash(&ids.len(), state);
+                        for #id_name in ids {
+                            let Some(__item) = self.get(#id_name) else {
+                                continue;
+                            };
+                            ::core::hash::Hash::hash(&#id_name, state);
+                            #(#hash)*
+                        }
+                    }
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        let struct_of_diff = if struct_diff {
+            let patch_name = syn::Ident::new(
+                &format!("{struct_name}Patch"),
+                proc_macro2::Span::call_site(),
+            );
+            let changes_name = syn::Ident::new(
+                &format!("{struct_name}Changes"),
+                proc_macro2::Span::call_site(),
+            );
+            let id_type_name = quote! { __Id }; // NOTE: mangled name to avoid conflicts
+
+            // Patch generics, with an added id type generic
+            let (generics_id, generics_id_self_use, generics_id_use) = {
+                let i = struct_generics
+                    .params
+                    .iter()
+                    .position(|param| !matches!(param, syn::GenericParam::Lifetime(_)))
+                    .unwrap_or(struct_generics.params.len());
+                let mut params: Vec<_> = struct_generics
+                    .params
+                    .iter()
+                    .map(|param| quote! { #param })
+                    .collect();
+                params.insert(i, quote! { #id_type_name });
+                let mut params_use: Vec<_> = struct_generics
+                    .params
+                    .iter()
+                    .map(|param| match param {
+                        syn::GenericParam::Type(param) => {
+                            let ident = &param.ident;
+                            quote! { #ident }
+                        }
+                        syn::GenericParam::Lifetime(param) => {
+                            let ident = &param.lifetime;
+                            quote! { #ident }
+                        }
+                        syn::GenericParam::Const(param) => {
+                            let ident = &param.ident;
+                            quote! { #ident }
+                        }
+                    })
+                    .collect();
+                let mut params_self_use = params_use.clone();
+                params_self_use.insert(i, id_type_name.clone());
+                params_use.insert(i, quote! { #generic_family_name::Id });
+                (
+                    quote! { #(#params),* },
+                    quote! { #(#params_self_use),* },
+                    quote! { #(#params_use),* },
+                )
+            };
+
+            let changes_fields = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    let ty = &field.ty;
+                    quote! { pub #name: Option<#ty>, }
+                })
+                .collect::<Vec<_>>();
+
+            let constraints = struct_fields
+                .iter()
+                .map(|field| {
+                    let ty = &field.ty;
+                    quote! { #ty: ::core::clone::Clone + ::core::cmp::PartialEq, }
+                })
+                .collect::<Vec<_>>();
+
+            // Clone the component out of the `Ref` struct
+            let owned = |field: &Field, item: TokenStream| {
+                let name = &field.name;
+                if field.presence.is_some() {
+                    quote! { #item.#name.cloned() }
+                } else if field.tag {
+                    quote! { *#item.#name }
+                } else {
+                    quote! { ::core::clone::Clone::clone(#item.#name) }
+                }
+            };
+            let added = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    let value = owned(field, quote! { __new });
+                    quote! { #name: #value, }
+                })
+                .collect::<Vec<_>>();
+            let changed = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    let value = owned(field, quote! { __new });
+                    quote! {
+                        #name: if __old.#name != __new.#name {
+                            __changed = true;
+                            Some(#value)
+                        } else {
+                            None
+                        },
+                    }
+                })
+                .collect::<Vec<_>>();
+            let apply_changes = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    let set = if field.presence.is_some() {
+                        quote! { self.#name.replace(#id_name, value); }
+                    } else if field.tag {
+                        quote! { self.#name.set(#id_name, value); }
+                    } else {
+                        quote! {
+                            if let Some(component) = self.#name.get_mut(#id_name) {
+                                *component = value;
+                            }
+                        }
+                    };
+                    quote! {
+                        if let Some(value) = __changes.#name {
+                            #set
+                        }
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            // NOTE: the traits are implemented manually, so that they are only required
+            // from the components when they are used (unlike with a `derive`),
+            // and the bounds are higher-ranked, so that the unsatisfied ones are not an error
+            let patch_constraints = |bound: TokenStream| {
+                quote! {
+                    #id_type_name: #bound,
+                    for<#lifetime_ref_name> #struct_name<#generics_use>: #bound,
+                    for<#lifetime_ref_name> #changes_name<#generics_use>: #bound,
+                }
+            };
+            let patch_debug_constraints = patch_constraints(quote! { ::core::fmt::Debug });
+            let patch_clone_constraints = patch_constraints(quote! { ::core::clone::Clone });
+            let patch_eq_constraints = patch_constraints(quote! { ::core::cmp::PartialEq });
+            let changes_constraints = |bound: TokenStream| {
+                struct_fields
+                    .iter()
+                    .map(|field| {
+                        let ty = &field.ty;
+                        quote! { for<#lifetime_ref_name> #ty: #bound, }
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let changes_debug_constraints = changes_constraints(quote! { ::core::fmt::Debug });
+            let changes_clone_constraints = changes_constraints(quote! { ::core::clone::Clone });
+            let changes_eq_constraints = changes_constraints(quote! { ::core::cmp::PartialEq });
+            let changes_debug = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    let label = name.to_string();
+                    quote! { ::core::fmt::DebugStruct::field(&mut __debug, #label, &self.#name); }
+                })
+                .collect::<Vec<_>>();
+            let changes_clone = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    quote! { #name: ::core::clone::Clone::clone(&self.#name), }
+                })
+                .collect::<Vec<_>>();
+            let changes_eq = struct_fields
+                .iter()
+                .map(|field| {
+                    let name = &field.name;
+                    quote! { && self.#name == other.#name }
+                })
+                .collect::<Vec<_>>();
+            let patch_name_str = patch_name.to_string();
+            let changes_name_str = changes_name.to_string();
+
+            let patch_doc = format!(
+                r#"Generated by `#[derive(SplitFields)]`.
+The difference between two states of [`{struct_of_name}`], created by [`{struct_of_name}::diff`]."#
+            );
+            let changes_doc = format!(
+                r#"Generated by `#[derive(SplitFields)]`.
+The changed fields of an entity of [`{struct_of_name}`], where `None` means that the field is unchanged."#
+            );
+
+            quote! {
+                #[doc = #patch_doc]
+                #vis struct #patch_name<#generics_id> {
+                    /// The entities present only in the new state.
+                    pub added: ::stecs::__private::Vec<(#id_type_name, #struct_name<#generics_use>)>,
+                    /// The id's of the entities present only in the old state.
+                    pub removed: ::stecs::__private::Vec<#id_type_name>,
+                    /// The changed fields of the entities present in both states.
+                    pub changed: ::stecs::__private::Vec<(#id_type_name, #changes_name<#generics_use>)>,
+                }
+
+                #[doc = #changes_doc]
+                #vis struct #changes_name<#generics> {
+                    #(#changes_fields)*
+                }
+
+                impl<#generics_id> ::core::fmt::Debug for #patch_name<#generics_id_self_use>
+                where
+                    #patch_debug_constraints
+                {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        // NOTE: `DebugStruct::field` is called explicitly, since `Fields::field` may be in scope
+                        let mut __debug = f.debug_struct(#patch_name_str);
+                        ::core::fmt::DebugStruct::field(&mut __debug, "added", &self.added);
+                        ::core::fmt::DebugStruct::field(&mut __debug, "removed", &self.removed);
+                        ::core::fmt::DebugStruct::field(&mut __debug, "changed", &self.changed);
+                        __debug.finish()
+                    }
+                }
+
+                impl<#generics_id> ::core::clone::Clone for #patch_name<#generics_id_self_use>
+                where
+                    #patch_clone_constraints
+                {
+                    fn clone(&self) -> Self {
+                        Self {
+                            added: ::core::clone::Clone::clone(&self.added),
+                            removed: ::core::clone::Clone::clone(&self.removed),
+                            changed: ::core::clone::Clone::clone(&self.changed),
+                        }
+                    }
+                }
+
+                impl<#generics_id> ::core::cmp::PartialEq for #patch_name<#generics_id_self_use>
+                where
+                    #patch_eq_constraints
+                {
+                    fn eq(&self, other: &Self) -> bool {
+                        self.added == other.added && self.removed == other.removed && self.changed == other.changed
+                    }
+                }
+
+                impl<#generics> ::core::fmt::Debug for #changes_name<#generics_use>
+                where
+                    #(#changes_debug_constraints)*
+                {
+                    fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                        let mut __debug = f.debug_struct(#changes_name_str);
+                        #(#changes_debug)*
+                        __debug.finish()
+                    }
+                }
+
+                impl<#generics> ::core::clone::Clone for #changes_name<#generics_use>
+                where
+                    #(#changes_clone_constraints)*
+                {
+                    fn clone(&self) -> Self {
+                        Self {
+                            #(#changes_clone)*
+                        }
+                    }
+                }
+
+                impl<#generics> ::core::cmp::PartialEq for #changes_name<#generics_use>
+                where
+                    #(#changes_eq_constraints)*
+                {
+                    fn eq(&self, other: &Self) -> bool {
+                        true #(#changes_eq)*
+                    }
+                }
+
+                // NOTE: the patch refers to the entities by their id's,
+                // which must not move when other entities are removed
+                impl<#generics_family> #struct_of_name<#generics_family_use>
+                where
+                    #generic_family_name: ::stecs::storage::StableFamily,
+                    #(#constraints)*
+                {
+                    /// Compare two states of the archetype, e.g. from the previous and the current tick,
+                    /// matching the entities by their id's.
+                    ///
+                    /// Applying the patch to `old` with [`Self::apply`] turns it into `new`,
+                    /// and the reverse patch (`diff(new, old)`) can be used to undo the changes.
+                    pub fn diff(old: &Self, new: &Self) -> #patch_name<#generics_id_use> {
+                        use ::stecs::storage::Storage;
+                        let mut patch = #patch_name {
+                            added: ::stecs::__private::Vec::new(),
+                            removed: ::stecs::__private::Vec::new(),
+                            changed: ::stecs::__private::Vec::new(),
+                        };
+                        for #id_name in old.ids.ids() {
+                            if new.ids.get(#id_name).is_none() {
+                                patch.removed.push(#id_name);
+                            }
+                        }
+                        for #id_name in new.ids.ids() {
+                            let Some(__new) = new.get(#id_name) else {
+                                continue;
+                            };
+                            let Some(__old) = old.get(#id_name) else {
+                                patch.added.push((#id_name, #struct_name { #(#added)* }));
+                                continue;
+                            };
+                            let mut __changed = false;
+                            let __changes = #changes_name { #(#changed)* };
+                            if __changed {
+                                patch.changed.push((#id_name, __changes));
+                            }
+                        }
+                        patch
+                    }
+
+                    /// Apply a patch created by [`Self::diff`], keeping the id's of the entities.
+                    ///
+                    /// The added entities are inserted with [`insert_with_id`](::stecs::archetype::Archetype::insert_with_id),
+                    /// and the ones that could not be inserted (e.g. because the family does not support choosing the id's) are returned back.
+                    ///
+                    /// The patch is not applied atomically: when `Err` is returned,
+                    /// the removals, the changes, and the rest of the additions have already been applied.
+                    pub fn apply(
+                        &mut self,
+                        patch: #patch_name<#generics_id_use>,
+                    ) -> Result<(), ::stecs::__private::Vec<(#generic_family_name::Id, #struct_name<#generics_use>)>> {
+                        use ::stecs::archetype::Archetype;
+                        use ::stecs::storage::Storage;
+                        for #id_name in patch.removed {
+                            self.remove(#id_name);
+                        }
+                        for (#id_name, __changes) in patch.changed {
+                            #(#apply_changes)*
+                        }
+                        let rejected: ::stecs::__private::Vec<_> = patch
+                            .added
+                            .into_iter()
+                            .filter_map(|(#id_name, value)| {
+                                self.insert_with_id(#id_name, value).err().map(|value| (#id_name, value))
+                            })
+                            .collect();
+                        if rejected.is_empty() {
+                            Ok(())
+                        } else {
+                            Err(rejected)
+                        }
+                    }
                 }
             }
+        } else {
+            quote! {}
         };
 
         let struct_of_default = {
@@ -671,6 +2207,13 @@ The given `ids` must not repeat and must be valid and present id's in the storag
         generated.append_all(struct_of_clone);
         generated.append_all(struct_of_impl);
         generated.append_all(struct_of_archetype);
+        generated.append_all(struct_of_optional);
+        generated.append_all(struct_of_archetype_ref);
+        generated.append_all(struct_of_has_field);
+        generated.append_all(struct_of_debug);
+        generated.append_all(struct_of_eq);
+        generated.append_all(struct_of_hash);
+        generated.append_all(struct_of_diff);
         generated.append_all(struct_of_default);
         generated
     }
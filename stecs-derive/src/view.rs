@@ -0,0 +1,134 @@
+use crate::{
+    get::{ImageOpts, StructFieldOpts},
+    optic::{Optic, OpticComponent, OpticStorage},
+    query::{QueryArchetype, QueryOpts},
+    syn,
+};
+
+use darling::{ast, FromDeriveInput, FromField};
+use proc_macro2::TokenStream;
+use quote::quote;
+
+#[derive(FromDeriveInput)]
+#[darling(supports(struct_named), attributes(query))]
+pub struct ViewOpts {
+    ident: syn::Ident,
+    data: ast::Data<(), FieldOpts>,
+    generics: syn::Generics,
+    /// The type of the archetype to query from, e.g. `StructOf<Vec<Unit>>`.
+    archetype: syn::Type,
+}
+
+#[derive(FromField)]
+#[darling(attributes(query))]
+struct FieldOpts {
+    ident: Option<syn::Ident>,
+    ty: syn::Type,
+    /// The optic to access the component, e.g. `damage.Get.Some`.
+    optic: Option<String>,
+}
+
+impl ViewOpts {
+    pub fn derive(self) -> TokenStream {
+        match self.derive_impl() {
+            Ok(tokens) => tokens,
+            Err(err) => err.to_compile_error(),
+        }
+    }
+
+    fn derive_impl(self) -> syn::Result<TokenStream> {
+        let Self {
+            ident: view_name,
+            data,
+            generics,
+            archetype,
+        } = self;
+
+        let lifetime = generics
+            .lifetimes()
+            .next()
+            .map(|param| param.lifetime.clone())
+            .ok_or_else(|| {
+                syn::Error::new_spanned(&view_name, "the view must have a lifetime parameter")
+            })?;
+
+        let fields = data
+            .take_struct()
+            .ok_or_else(|| syn::Error::new_spanned(&view_name, "not a struct"))?
+            .fields;
+        let fields = fields
+            .into_iter()
+            .map(|field| {
+                let name = field
+                    .ident
+                    .ok_or_else(|| syn::Error::new_spanned(&field.ty, "field has no name"))?;
+                let is_mut = matches!(
+                    &field.ty,
+                    syn::Type::Reference(reference) if reference.mutability.is_some()
+                );
+                let optic = match field.optic {
+                    Some(optic) => syn::parse_str::<Optic>(&optic)
+                        .map_err(|err| syn::Error::new_spanned(&name, err))?,
+                    // NOTE: same as the shorthand struct syntax in `query!`
+                    None if name == "id" => Optic::GetId,
+                    None => Optic::Access {
+                        storage: OpticStorage::Field {
+                            name: name.clone(),
+                            optic: Box::new(OpticStorage::Identity),
+                        },
+                        component: OpticComponent::Identity,
+                    },
+                };
+                Ok(StructFieldOpts {
+                    name,
+                    is_mut,
+                    optic,
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        let is_mut = fields.iter().any(|field| field.is_mut);
+        #[cfg(not(feature = "query_mut"))]
+        if is_mut {
+            return Err(syn::Error::new_spanned(
+                &view_name,
+                "enable the `query_mut` feature flag to allow mutable queries",
+            ));
+        }
+
+        let image = ImageOpts::Struct {
+            ident: view_name.clone(),
+            fields: fields.into_iter().collect(),
+        };
+        let archetype_expr: syn::Expr = syn::parse_quote! { __archetype }; // NOTE: mangled name to avoid conflicts
+
+        let query =
+            QueryOpts::new(vec![QueryArchetype::from(archetype_expr.clone())], image).query();
+
+        let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+        let methods = if is_mut {
+            quote! {
+                /// Query this view mutably from the archetype.
+                pub fn query_mut(#archetype_expr: &#lifetime mut #archetype) -> impl Iterator<Item = Self> + #lifetime {
+                    use ::stecs::storage::Storage;
+                    #query
+                }
+            }
+        } else {
+            quote! {
+                /// Query this view from the archetype.
+                pub fn query(#archetype_expr: &#lifetime #archetype) -> impl Iterator<Item = Self> + #lifetime {
+                    use ::stecs::storage::Storage;
+                    #query
+                }
+            }
+        };
+
+        Ok(quote! {
+            impl #impl_generics #view_name #ty_generics #where_clause {
+                #methods
+            }
+        })
+    }
+}